@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+    timeout::{OnTimeout, TimeoutReport, Timer},
+};
+
+/// Timer injected into the collection: the async runtime's sleep, plus a
+/// report of every exceeded deadline.
+struct TokioTimer;
+
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> {
+        tokio::time::sleep(duration)
+    }
+
+    fn on_timeout(&self, report: &TimeoutReport) {
+        println!(
+            "[{}] {}::{} exceeded its {:?} deadline",
+            report.schedule, report.plugin, report.system, report.timeout
+        );
+    }
+}
+
+/// Awaits a socket that never answers. Without a deadline, the whole
+/// loop would be stalled forever.
+struct StalledSocketPlugin {
+    timeouts: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for StalledSocketPlugin {
+    const ASYNC_TIMEOUT: Option<Duration> = Some(Duration::from_millis(100));
+
+    fn build() -> Self {
+        Self { timeouts: 0 }
+    }
+
+    async fn async_update(&mut self, _sd: &SD) {
+        std::future::pending::<()>().await;
+    }
+
+    fn on_async_timeout(&mut self, system: &'static str, _sd: &SD) {
+        self.timeouts += 1;
+        println!(
+            "StalledSocketPlugin: {system} cancelled ({} times)",
+            self.timeouts
+        );
+    }
+}
+
+/// Its writes take longer than a frame, but matter: each is carried over
+/// to the next frames instead of being cancelled, and its result applied
+/// once done.
+struct SlowDiskPlugin {
+    writes: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for SlowDiskPlugin {
+    const ASYNC_TIMEOUT: Option<Duration> = Some(Duration::from_millis(50));
+    const ON_ASYNC_TIMEOUT: OnTimeout = OnTimeout::CarryOver;
+
+    fn build() -> Self {
+        Self { writes: 0 }
+    }
+
+    fn async_task(
+        &mut self,
+        _sd: &SD,
+    ) -> Option<impl Future<Output = impl FnOnce(&mut Self, &mut SD) + use<SD>> + use<SD>> {
+        Some(async {
+            tokio::time::sleep(Duration::from_millis(180)).await;
+            |plugin: &mut Self, _sd: &mut SD| {
+                plugin.writes += 1;
+                println!("SlowDiskPlugin: write {} done", plugin.writes);
+            }
+        })
+    }
+}
+
+struct ExitAfterThreeFramesPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ExitAfterThreeFramesPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == 3 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "profile")]
+    typed_ecs::profile::setup_default_profiling();
+
+    generate_collection!(
+        StalledSocketPlugin,
+        SlowDiskPlugin,
+        ExitAfterThreeFramesPlugin
+    );
+    let collection = build_generated_collection::<PhantomSharedData>().with_timer(TokioTimer);
+
+    let start = Instant::now();
+    App::new(collection).run().await;
+    println!("App terminated after {:?}", start.elapsed());
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use typed_ecs::{
+    app::App,
+    futures::{Stream, stream},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+trait PacketMemory {
+    fn last_packet(&self) -> Option<u32>;
+    fn set_last_packet(&mut self, packet: u32);
+}
+
+struct SDimpl {
+    last_packet: Option<u32>,
+}
+
+impl SharedData for SDimpl {
+    fn build() -> Self {
+        Self { last_packet: None }
+    }
+}
+
+impl PacketMemory for SDimpl {
+    fn last_packet(&self) -> Option<u32> {
+        self.last_packet
+    }
+    fn set_last_packet(&mut self, packet: u32) {
+        self.last_packet = Some(packet);
+    }
+}
+
+/// Owns a long-lived network reader, which lives as long as the app runs,
+/// independently of the frames.
+struct NetworkReaderPlugin {
+    received: u32,
+}
+
+impl<SD: SharedData + PacketMemory> Plugin<SD> for NetworkReaderPlugin {
+    fn build() -> Self {
+        Self { received: 0 }
+    }
+
+    fn background_task(
+        &mut self,
+    ) -> impl Stream<Item = impl FnOnce(&mut Self, &mut SD) + use<SD>> + use<SD> {
+        // Each packet is handed to the plugin and the shared data, right
+        // before the ApplyAsyncUpdate schedule of the next frame.
+        stream::unfold(0, |packet| async move {
+            // Simulates waiting on a socket.
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            let apply = move |plugin: &mut Self, sd: &mut SD| {
+                plugin.received += 1;
+                sd.set_last_packet(packet);
+            };
+            Some((apply, packet + 1))
+        })
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        println!("{} packets received", self.received);
+    }
+}
+
+/// Frame pacing: each frame lasts ~100ms.
+struct FramePlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData + PacketMemory> Plugin<SD> for FramePlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn update(&mut self, sd: &SD) {
+        println!("Frame {}: last packet {:?}", self.frames, sd.last_packet());
+    }
+
+    async fn async_update(&mut self, _sd: &SD) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == 5 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "profile")]
+    typed_ecs::profile::setup_default_profiling();
+
+    generate_collection!(NetworkReaderPlugin, FramePlugin);
+    let collection: GeneratedPluginCollection<SDimpl> = build_generated_collection();
+    App::new(collection).run().await;
+}
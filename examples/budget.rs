@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use typed_ecs::{
+    app::App,
+    budget::{BudgetViolation, OnBudgetExceeded, Watchdog},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::{ExitReason, ShouldExit},
+};
+
+/// Usually within its 1ms budget, but every 5th frame takes 3ms.
+struct JitteryPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for JitteryPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn budget(system: &'static str) -> Option<Duration> {
+        match system {
+            "update" => Some(Duration::from_millis(1)),
+            _ => None,
+        }
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        self.frames += 1;
+        if self.frames.is_multiple_of(5) {
+            std::thread::sleep(Duration::from_millis(3));
+        }
+    }
+}
+
+/// Gets slower every frame, until it blows the budget of the whole
+/// Update schedule.
+struct SlowingDownPlugin {
+    delay: Duration,
+}
+
+impl<SD: SharedData> Plugin<SD> for SlowingDownPlugin {
+    fn build() -> Self {
+        Self {
+            delay: Duration::ZERO,
+        }
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        self.delay += Duration::from_millis(1);
+        std::thread::sleep(self.delay);
+    }
+}
+
+/// Logs every violation, and stops the app once the Update schedule
+/// misses its 20ms deadline.
+struct FrameWatchdog;
+
+impl Watchdog for FrameWatchdog {
+    type Instant = Instant;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn elapsed(&self, since: Instant) -> Duration {
+        since.elapsed()
+    }
+
+    fn schedule_budget(&self, schedule: &'static str) -> Option<Duration> {
+        match schedule {
+            "Update" => Some(Duration::from_millis(20)),
+            _ => None,
+        }
+    }
+
+    fn on_violation(&self, violation: &BudgetViolation) -> OnBudgetExceeded {
+        println!(
+            "{}::{}::{}: {:?} over a {:?} budget",
+            violation.schedule,
+            violation.plugin.unwrap_or("-"),
+            violation.system.unwrap_or("-"),
+            violation.elapsed,
+            violation.budget
+        );
+        match violation.plugin {
+            None => OnBudgetExceeded::Exit,
+            Some(_) => OnBudgetExceeded::Continue,
+        }
+    }
+}
+
+struct NeverExitPlugin;
+
+impl<SD: SharedData> Plugin<SD> for NeverExitPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, _should_exit: &mut S, _sd: &SD) {}
+}
+
+#[tokio::main]
+async fn main() {
+    generate_collection!(JitteryPlugin, SlowingDownPlugin, NeverExitPlugin);
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    let mut app = App::new(collection.with_watchdog(FrameWatchdog));
+    let reason = app.run().await;
+
+    println!("Exit reason: {reason:?}");
+    let ExitReason::BudgetExceeded(violation) = reason else {
+        panic!("the Update schedule should have exceeded its budget");
+    };
+    assert_eq!(violation.schedule, "Update");
+    assert_eq!(violation.plugin, None);
+    // JitteryPlugin's 3ms frames are counted as well.
+    assert!(app.budget_violations() > 1);
+}
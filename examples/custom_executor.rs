@@ -1,76 +1,93 @@
-use core::marker::PhantomData;
+use std::time::Instant;
 use typed_ecs::macros::generate_collection;
-use typed_ecs::should_exit::ShouldExit;
+use typed_ecs::should_exit::{ExitReason, ShouldExit};
 use typed_ecs::{
     app::App,
     executor::ExecutorTrait,
+    instrumentation::Instrumentation,
     plugin::Plugin,
     plugin_collection::PluginCollection,
     shared_data::{PhantomSharedData, SharedData},
 };
 
-struct EmptyPlugin;
+struct ExitAfterThreeFramesPlugin {
+    frames: u32,
+}
 
-impl<SD: SharedData> Plugin<SD> for EmptyPlugin {
+impl<SD: SharedData> Plugin<SD> for ExitAfterThreeFramesPlugin {
     fn build() -> Self {
-        Self
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == 3 {
+            should_exit.request_exit();
+        }
     }
 }
 
-pub struct CustomExecutor;
+/// Only overrides the hooks: the schedule order, the background tasks
+/// and the exit logic are the ones of the default `run`.
+pub struct CustomExecutor {
+    frame: u32,
+    frame_start: Instant,
+}
 
-impl ExecutorTrait for CustomExecutor {
-    fn init() -> Self {
-        Self
+impl CustomExecutor {
+    fn new() -> Self {
+        Self {
+            frame: 0,
+            frame_start: Instant::now(),
+        }
     }
-    async fn run<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
+}
+
+impl ExecutorTrait for CustomExecutor {
+    fn before_frame<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
         &mut self,
-        app: &mut App<SD, PC, Executor>,
+        _app: &mut App<SD, PC, Executor, I>,
     ) {
-        let mut should_exit = false;
-
-        app.plugin_collection.startup_all(&app.shared_data);
-        app.plugin_collection
-            .apply_startup_all(&mut app.shared_data);
-
-        app.plugin_collection
-            .async_startup_all(&app.shared_data)
-            .await;
-        app.plugin_collection
-            .apply_async_startup_all(&mut app.shared_data);
-
-        loop {
-            app.plugin_collection.pre_update_all(&app.shared_data);
-            app.plugin_collection
-                .apply_pre_update_all(&mut app.shared_data);
-
-            app.plugin_collection.update_all(&app.shared_data);
-            app.plugin_collection.apply_update_all(&mut app.shared_data);
-
-            app.plugin_collection.post_update_all(&app.shared_data);
-            app.plugin_collection
-                .apply_post_update_all(&mut app.shared_data);
-
-            app.plugin_collection
-                .exit_check_all(&mut should_exit, &app.shared_data);
-
-            if should_exit.is_true() {
-                println!("Exiting...");
-                break;
-            }
+        self.frame_start = Instant::now();
+    }
 
-            app.plugin_collection
-                .async_update_all(&app.shared_data)
-                .await;
-            app.plugin_collection
-                .apply_async_update_all(&mut app.shared_data);
+    fn after_frame<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        _app: &mut App<SD, PC, Executor, I>,
+        should_exit: bool,
+    ) {
+        println!("Frame {} took {:?}", self.frame, self.frame_start.elapsed());
+        self.frame += 1;
+        if should_exit {
+            println!("Exiting...");
         }
     }
 
-    fn run_exit_hooks<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
-        app: &mut App<SD, PC, Executor>,
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+        reason: &ExitReason,
     ) {
-        app.plugin_collection.on_exit_all(&app.shared_data);
+        println!(
+            "Running the exit hooks ({reason:?}) after {} frames",
+            self.frame
+        );
+        app.run_shutdown();
     }
 }
 
@@ -80,10 +97,11 @@ async fn main() {
     typed_ecs::profile::setup_default_profiling();
 
     println!("Beginning of the `main` function...");
-    generate_collection!(EmptyPlugin);
+    generate_collection!(ExitAfterThreeFramesPlugin);
     let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
-    App::new_with_executor(collection, PhantomData::<CustomExecutor>)
+    let reason = App::new_with_executor(collection, CustomExecutor::new())
         .run()
         .await;
+    assert_eq!(reason, ExitReason::Requested);
     println!("Ending of the `main` function...");
 }
@@ -0,0 +1,102 @@
+use typed_ecs::{
+    app::App,
+    dependencies::{Conflicts, Requires},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+#[derive(Default)]
+struct World {
+    time: f32,
+    position: f32,
+}
+
+impl SharedData for World {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// Advances the clock by a fixed step.
+struct TimePlugin;
+
+impl Plugin<World> for TimePlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_pre_update(&mut self, sd: &mut World) {
+        sd.time += 0.1;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &World) {
+        if sd.time >= 1.0 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+/// Moves at constant speed, using the clock of `TimePlugin`.
+struct PhysicsPlugin;
+
+impl Plugin<World> for PhysicsPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut World) {
+        sd.position = 2.0 * sd.time;
+    }
+
+    fn on_exit(&mut self, sd: &World) {
+        println!("Moved to {:.1} in {:.1}s", sd.position, sd.time);
+    }
+}
+
+impl Requires for PhysicsPlugin {
+    type Plugins = (TimePlugin,);
+}
+
+/// Prints the position every frame.
+struct ConsoleLogPlugin;
+
+impl Plugin<World> for ConsoleLogPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn post_update(&mut self, sd: &World) {
+        println!("{:.1}s: {:.1}", sd.time, sd.position);
+    }
+}
+
+/// Drops the logs: only one logger can be in a collection.
+#[allow(dead_code)]
+struct NullLogPlugin;
+
+impl Plugin<World> for NullLogPlugin {
+    fn build() -> Self {
+        Self
+    }
+}
+
+impl Conflicts<ConsoleLogPlugin> for NullLogPlugin {}
+
+#[tokio::main]
+async fn main() {
+    generate_collection!(PhysicsPlugin, TimePlugin, ConsoleLogPlugin);
+    App::new(build_generated_collection::<World>()).run().await;
+
+    // Without `TimePlugin`, the collection doesn't compile:
+    //
+    // error[E0277]: `PhysicsPlugin` requires the `TimePlugin` plugin, missing from the collection
+    //    |
+    //    |     generate_collection!(PhysicsPlugin);
+    //    |     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ add `TimePlugin` to this collection
+    //
+    // And neither does one with both loggers:
+    //
+    // error[E0277]: `NullLogPlugin` and `ConsoleLogPlugin` conflict: they can't be in the same collection
+}
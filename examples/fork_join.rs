@@ -0,0 +1,94 @@
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use seq_macro::seq;
+use typed_ecs::{
+    app::App,
+    fork_join::{ForkJoin, StdThreads},
+    macros::generate_collection,
+    plugin::Plugin,
+    plugin_collection::PluginCollection,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+};
+
+struct ExitPlugin;
+
+impl<SD: SharedData> Plugin<SD> for ExitPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        should_exit.request_exit();
+    }
+}
+
+seq!(N in 1..=8 {
+    struct Plugin~N;
+
+    impl<SD: SharedData> Plugin<SD> for Plugin~N {
+        fn build() -> Self {
+            Self
+        }
+        fn update(&mut self, _sd: &SD) {
+            sleep(Duration::from_millis(200));
+        }
+    }
+});
+
+/// A custom backend only has to run two closures concurrently, and wait for
+/// both. On a dual core MCU, `b` would be handed to the second core (e.g. with
+/// `embassy_rp::multicore`), while `a` runs on the current one. Here, we
+/// simply forward to the `std` reference backend, counting the forks.
+struct CountingForkJoin {
+    forks: std::sync::atomic::AtomicUsize,
+}
+
+impl ForkJoin for CountingForkJoin {
+    fn join<A, B>(&self, a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send,
+    {
+        self.forks
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        StdThreads.join(a, b);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "profile")]
+    typed_ecs::profile::setup_default_profiling();
+
+    seq!(N in 1..=8 {
+        generate_collection!(#(Plugin~N,)* ExitPlugin);
+    });
+
+    // 8 plugins sleeping 200ms in `update`: 1.6s sequentially, 200ms in parallel.
+    let collection: GeneratedPluginCollection<PhantomSharedData, StdThreads> =
+        build_generated_collection_with(StdThreads);
+    let start = Instant::now();
+    App::new(collection).run().await;
+    let elapsed = start.elapsed();
+    println!("StdThreads backend: {elapsed:?}");
+    assert!(elapsed < Duration::from_millis(800));
+
+    let collection: GeneratedPluginCollection<PhantomSharedData, CountingForkJoin> =
+        build_generated_collection_with(CountingForkJoin { forks: 0.into() });
+    let mut app = App::new(collection);
+    app.run().await;
+    // The app runs a single frame. Each of its 5 non-applying schedules
+    // (Startup, PreUpdate, Update, PostUpdate and OnExit) forks the 9
+    // plugins in a tree of 8 joins.
+    let joins = app
+        .plugin_collection
+        .fork_join()
+        .forks
+        .load(std::sync::atomic::Ordering::Relaxed);
+    println!("Custom backend: {joins} joins");
+    assert_eq!(joins, 5 * 8);
+}
@@ -0,0 +1,147 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use typed_ecs::{
+    app::App,
+    idle::{Idle, IdleExecutor, Wait},
+    inject::{Inject, InjectPlugin, IsrQueue},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+const PRESSES: u32 = 5;
+
+#[derive(Default)]
+struct Board {
+    presses: u32,
+    led: bool,
+    frames: u32,
+}
+
+impl SharedData for Board {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// What `wfi` waits for on a board: an interrupt having fired.
+#[derive(Default)]
+struct Event {
+    fired: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Event {
+    fn signal(&self) {
+        *self.fired.lock().unwrap() = true;
+        self.condvar.notify_one();
+    }
+}
+
+/// Stands for `cortex_m::asm::wfi()` (and for a timer interrupt, for the
+/// deadline).
+struct CondvarWait(Arc<Event>);
+
+impl Wait for CondvarWait {
+    async fn wait(&mut self, timeout: Option<Duration>) {
+        let Event { fired, condvar } = &*self.0;
+        let fired = fired.lock().unwrap();
+        let mut fired = match timeout {
+            None => condvar.wait_while(fired, |fired| !*fired).unwrap(),
+            Some(timeout) => {
+                condvar
+                    .wait_timeout_while(fired, timeout, |fired| !*fired)
+                    .unwrap()
+                    .0
+            }
+        };
+        *fired = false;
+    }
+}
+
+// Pushed by the button interrupt handler, a thread here.
+static BUTTON: IsrQueue<(), 4> = IsrQueue::new();
+
+struct Button;
+
+impl Inject<Board> for Button {
+    type Source = IsrQueue<(), 4>;
+
+    fn source() -> &'static Self::Source {
+        &BUTTON
+    }
+
+    fn inject(sd: &mut Board, _press: ()) {
+        sd.presses += 1;
+        println!("button pressed ({})", sd.presses);
+    }
+}
+
+type ButtonPlugin = InjectPlugin<Button>;
+
+/// Toggles the LED every 100ms: idle until then.
+struct BlinkPlugin {
+    next_toggle: Instant,
+}
+
+impl Plugin<Board> for BlinkPlugin {
+    fn build() -> Self {
+        Self {
+            next_toggle: Instant::now(),
+        }
+    }
+
+    fn apply_update(&mut self, sd: &mut Board) {
+        sd.frames += 1;
+        if Instant::now() >= self.next_toggle {
+            sd.led = !sd.led;
+            self.next_toggle += Duration::from_millis(100);
+        }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Board) {
+        if sd.presses == PRESSES {
+            should_exit.request_exit();
+        }
+    }
+
+    fn idle(&self, _sd: &Board) -> Idle {
+        Idle::For(self.next_toggle.saturating_duration_since(Instant::now()))
+    }
+}
+
+generate_collection!(ButtonPlugin, BlinkPlugin);
+
+// `CondvarWait` blocks the thread, as `wfi` blocks the core.
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let event = Arc::new(Event::default());
+    let presses = thread::spawn({
+        let event = event.clone();
+        move || {
+            for _ in 0..PRESSES {
+                thread::sleep(Duration::from_millis(150));
+                BUTTON.push(()).unwrap();
+                event.signal();
+            }
+        }
+    });
+
+    let start = Instant::now();
+    let mut app = App::new_with_executor(
+        build_generated_collection::<Board>(),
+        IdleExecutor::new(CondvarWait(event)),
+    );
+    app.run().await;
+    presses.join().unwrap();
+
+    // Frames only run on a button press or a blink: a few dozen, instead
+    // of millions with `DefaultExecutor`.
+    println!("{} frames in {:?}", app.shared_data.frames, start.elapsed());
+    assert!(app.shared_data.frames < 100);
+}
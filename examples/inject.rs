@@ -0,0 +1,95 @@
+use std::{thread, time::Duration};
+
+use typed_ecs::{
+    app::App,
+    inject::{Inject, InjectPlugin, IsrQueue},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+const SAMPLES: u32 = 10_000;
+
+#[derive(Default)]
+struct Motor {
+    // Last encoder reading, and how many have been applied.
+    position: u32,
+    readings: u32,
+    frames: u32,
+}
+
+impl SharedData for Motor {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+// Filled by the encoder interrupt handler, a thread here.
+static ENCODER: IsrQueue<u32, 32> = IsrQueue::new();
+
+struct Encoder;
+
+impl Inject<Motor> for Encoder {
+    type Source = IsrQueue<u32, 32>;
+
+    fn source() -> &'static Self::Source {
+        &ENCODER
+    }
+
+    fn inject(sd: &mut Motor, position: u32) {
+        // Readings arrive in order, none lost.
+        assert_eq!(position, sd.readings + 1);
+        sd.position = position;
+        sd.readings += 1;
+    }
+}
+
+type EncoderPlugin = InjectPlugin<Encoder>;
+
+/// Reads the position the encoder readings have been applied to.
+struct ControlPlugin;
+
+impl Plugin<Motor> for ControlPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Motor) {
+        sd.frames += 1;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Motor) {
+        if sd.readings == SAMPLES {
+            should_exit.request_exit();
+        }
+    }
+}
+
+generate_collection!(EncoderPlugin, ControlPlugin);
+
+#[tokio::main]
+async fn main() {
+    let producer = thread::spawn(|| {
+        for position in 1..=SAMPLES {
+            // An interrupt handler would drop the reading instead.
+            while ENCODER.push(position).is_err() {
+                thread::sleep(Duration::from_micros(10));
+            }
+        }
+    });
+
+    let mut app = App::new(build_generated_collection::<Motor>());
+    app.run().await;
+    producer.join().unwrap();
+
+    println!(
+        "{} readings applied in {} frames, up to {}, {} pushes rejected by a full queue",
+        app.shared_data.readings,
+        app.shared_data.frames,
+        app.shared_data.position,
+        ENCODER.dropped()
+    );
+    assert_eq!(app.shared_data.position, SAMPLES);
+    assert!(ENCODER.is_empty());
+}
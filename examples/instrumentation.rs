@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use typed_ecs::{
+    app::App,
+    instrumentation::Instrumentation,
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+};
+
+struct HelloPlugin;
+
+impl<SD: SharedData> Plugin<SD> for HelloPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn startup(&mut self, _sd: &SD) {
+        println!("    Hello!");
+    }
+}
+
+struct ExitAfterTwoFramesPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ExitAfterTwoFramesPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == 2 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+/// Counts the schedules and systems run, and logs the startup ones.
+#[derive(Default)]
+struct CountingLogger {
+    schedules: AtomicU32,
+    systems: AtomicU32,
+}
+
+/// Logs the end of a startup system.
+struct LogOnDrop(Option<(&'static str, &'static str)>);
+
+impl Drop for LogOnDrop {
+    fn drop(&mut self) {
+        if let Some((plugin, system)) = self.0 {
+            println!("  <- {plugin}::{system}");
+        }
+    }
+}
+
+impl Instrumentation for CountingLogger {
+    fn on_schedule_start(&self, _schedule: &'static str) -> impl Drop {
+        self.schedules.fetch_add(1, Ordering::Relaxed);
+        LogOnDrop(None)
+    }
+
+    fn on_system_start(
+        &self,
+        schedule: &'static str,
+        plugin: &'static str,
+        system: &'static str,
+    ) -> impl Drop {
+        self.systems.fetch_add(1, Ordering::Relaxed);
+        if schedule != "Startup" {
+            return LogOnDrop(None);
+        }
+        println!("  -> {plugin}::{system}");
+        LogOnDrop(Some((plugin, system)))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    generate_collection!(HelloPlugin, ExitAfterTwoFramesPlugin);
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    let mut app = App::new(collection).with_instrumentation(CountingLogger::default());
+    app.run().await;
+
+    let schedules = app.instrumentation.schedules.load(Ordering::Relaxed);
+    let systems = app.instrumentation.systems.load(Ordering::Relaxed);
+    println!("{schedules} schedules and {systems} systems run");
+    // 4 startup schedules, a frame of 13, a last frame stopping after
+    // ExitCheck (11), and OnExit.
+    assert_eq!(schedules, 4 + 13 + 11 + 1);
+    assert_eq!(systems, 2 * schedules);
+}
@@ -0,0 +1,144 @@
+use typed_ecs::{
+    app::App,
+    assert_footprint,
+    macros::{generate_collection, systems},
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+#[derive(Default)]
+struct World {
+    input: (f32, f32),
+    velocity: (f32, f32),
+    frames: u32,
+}
+
+impl SharedData for World {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+struct InputPlugin;
+
+#[systems]
+impl Plugin<World> for InputPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_pre_update(&mut self, sd: &mut World) {
+        sd.input = (1.0, 0.0);
+    }
+}
+
+struct PhysicsPlugin {
+    next_velocity: (f32, f32),
+}
+
+#[systems]
+impl Plugin<World> for PhysicsPlugin {
+    const NAME: &'static str = "physics::PhysicsPlugin";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const DESCRIPTION: &'static str = "Integrates the input into the velocity";
+
+    fn build() -> Self {
+        Self {
+            next_velocity: (0.0, 0.0),
+        }
+    }
+
+    fn update(&mut self, sd: &World) {
+        self.next_velocity = (sd.velocity.0 + sd.input.0, sd.velocity.1 + sd.input.1);
+    }
+
+    fn apply_update(&mut self, sd: &mut World) {
+        sd.velocity = self.next_velocity;
+    }
+}
+
+struct AudioPlugin;
+
+#[systems]
+impl Plugin<World> for AudioPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    async fn async_startup(&mut self, _sd: &World) {}
+
+    fn update(&mut self, _sd: &World) {}
+
+    async fn async_update(&mut self, _sd: &World) {}
+}
+
+struct FrameLimitPlugin;
+
+#[systems]
+impl Plugin<World> for FrameLimitPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_post_update(&mut self, sd: &mut World) {
+        sd.frames += 1;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &World) {
+        if sd.frames == 3 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+/// Without `#[systems]`, a plugin is listed apart, its systems unknown.
+struct UndeclaredPlugin;
+
+impl Plugin<World> for UndeclaredPlugin {
+    fn build() -> Self {
+        Self
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    generate_collection!(
+        InputPlugin,
+        PhysicsPlugin,
+        AudioPlugin,
+        FrameLimitPlugin,
+        UndeclaredPlugin
+    );
+    // Fails to compile if the app outgrows 8 KiB of RAM (the statistics
+    // of `system-stats` take most of it).
+    assert_footprint!(GeneratedPluginCollection<World>, 8 * 1024);
+
+    let mut app = App::new(build_generated_collection::<World>());
+    let info = app.collection_info();
+
+    // `cargo run --example introspection -- --dot | dot -Tsvg > schedules.svg`
+    if std::env::args().any(|arg| arg == "--dot") {
+        print!("{}", info.dot());
+    } else {
+        print!("{}", info.table());
+        println!();
+        for plugin in GeneratedPluginCollection::<World>::PLUGINS {
+            println!(
+                "{:<24} {:<8} {}",
+                plugin.name, plugin.version, plugin.description
+            );
+        }
+        println!();
+        print!("{}", info.footprint());
+    }
+
+    assert_eq!(info.plugins.len(), 5);
+    assert_eq!(info.plugins[1].name, "physics::PhysicsPlugin");
+    assert_eq!(info.plugins[0].name, "InputPlugin");
+    // ApplyUpdate
+    assert_eq!(info.plugins_in(9).count(), 1);
+    assert_eq!(info.unknown_plugins().count(), 1);
+    assert_eq!(info.shared_data.size, size_of::<World>());
+    app.run().await;
+}
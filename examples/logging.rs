@@ -0,0 +1,88 @@
+use typed_ecs::{
+    app::App,
+    logging::{self, Level, RingBuffer},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+};
+
+/// No allocation: the last 32 records, of up to 48 bytes each.
+static LOGS: RingBuffer<32, 48> = RingBuffer::new();
+
+struct ThermometerPlugin {
+    celsius: i32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ThermometerPlugin {
+    fn build() -> Self {
+        Self { celsius: 18 }
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        self.celsius += 3;
+        typed_ecs::debug!("read {}°C", self.celsius);
+        if self.celsius > 25 {
+            typed_ecs::warn!("too hot: {}°C", self.celsius);
+        }
+    }
+}
+
+struct ExitAfterFourFramesPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ExitAfterFourFramesPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        typed_ecs::trace!("frame {}", self.frames);
+        if self.frames == 4 {
+            typed_ecs::info!("exit requested");
+            should_exit.request_exit();
+        }
+    }
+}
+
+/// Drains the buffer once per frame, as a `no_std` app would to a serial
+/// port.
+struct LogDrainPlugin {
+    warnings: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for LogDrainPlugin {
+    fn build() -> Self {
+        Self { warnings: 0 }
+    }
+
+    fn apply_update(&mut self, _sd: &mut SD) {
+        LOGS.drain(|entry| {
+            if entry.level == Level::Warn {
+                self.warnings += 1;
+            }
+            println!("{entry:?}");
+        });
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        LOGS.drain(|entry| println!("{entry:?}"));
+        // 21°C, 24°C, 27°C and 30°C.
+        assert_eq!(self.warnings, 2);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    logging::set_sink(&LOGS).unwrap();
+    logging::set_max_level(Level::Debug);
+
+    generate_collection!(ThermometerPlugin, ExitAfterFourFramesPlugin, LogDrainPlugin);
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    App::new(collection).run().await;
+
+    assert_eq!(LOGS.overwritten(), 0);
+    assert_eq!(LOGS.dropped(), 0);
+}
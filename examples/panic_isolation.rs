@@ -0,0 +1,111 @@
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    panic_isolation::OnPanic,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::{ExitReason, ShouldExit},
+};
+
+/// Panics every other frame, but a fresh instance works fine.
+struct FlakySensorPlugin {
+    reads: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for FlakySensorPlugin {
+    const ON_PANIC: OnPanic = OnPanic::Rebuild;
+
+    fn build() -> Self {
+        println!("FlakySensorPlugin: built");
+        Self { reads: 0 }
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        self.reads += 1;
+        if self.reads == 2 {
+            panic!("sensor read failed");
+        }
+    }
+}
+
+/// Not essential: the app can go on without it.
+struct BrokenOverlayPlugin;
+
+impl<SD: SharedData> Plugin<SD> for BrokenOverlayPlugin {
+    const ON_PANIC: OnPanic = OnPanic::Skip;
+
+    fn build() -> Self {
+        Self
+    }
+
+    fn post_update(&mut self, _sd: &SD) {
+        panic!("overlay out of bounds");
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        unreachable!("a poisoned plugin is skipped, on_exit included");
+    }
+}
+
+/// The app can't run without it (default policy: `OnPanic::Exit`).
+struct CriticalPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for CriticalPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn apply_update(&mut self, _sd: &mut SD) {
+        self.frames += 1;
+        if self.frames == 5 {
+            panic!("invariant broken at frame {}", self.frames);
+        }
+    }
+}
+
+struct ExitAfterTenFramesPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ExitAfterTenFramesPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == 10 {
+            should_exit.request_exit();
+        }
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        println!("on_exit after {} frames", self.frames);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    #[cfg(feature = "profile")]
+    typed_ecs::profile::setup_default_profiling();
+
+    std::panic::set_hook(Box::new(|info| println!("Caught panic: {info}")));
+
+    generate_collection!(
+        FlakySensorPlugin,
+        BrokenOverlayPlugin,
+        CriticalPlugin,
+        ExitAfterTenFramesPlugin
+    );
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    let reason = App::new(collection).run().await;
+
+    println!("Exit reason: {reason:?}");
+    let ExitReason::PluginPanicked(report) = reason else {
+        panic!("CriticalPlugin should have stopped the app");
+    };
+    assert_eq!(report.plugin, "CriticalPlugin");
+    assert_eq!(report.message, "invariant broken at frame 5");
+}
@@ -1,7 +1,10 @@
 use seq_macro::seq;
 use std::{thread::sleep, time::Duration};
 use typed_ecs::macros::generate_collection;
+use typed_ecs::plugin_collection::PluginCollection;
 use typed_ecs::shared_data::PhantomSharedData;
+use typed_ecs::should_exit::ShouldExit;
+use typed_ecs::thread_pool::ThreadPoolConfig;
 use typed_ecs::{app::App, plugin::Plugin, shared_data::SharedData};
 
 struct ExitCounterPlugin;
@@ -11,8 +14,8 @@ impl<SD: SharedData> Plugin<SD> for ExitCounterPlugin {
         Self
     }
 
-    fn exit_check(&mut self, should_exit: &mut bool, sd: &SD) {
-        *should_exit = true;
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        should_exit.request_exit();
     }
 }
 
@@ -40,5 +43,13 @@ async fn main() {
 
     let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
 
-    App::new(collection).run().await;
+    // One worker per plugin, so that every sleeping `update` runs at the same time.
+    // Without `with_thread_pool`, rayon's global pool would be used instead.
+    let pool = ThreadPoolConfig::new()
+        .num_threads(GeneratedPluginCollection::<PhantomSharedData>::PLUGIN_NUM)
+        .thread_name(|i| format!("parallel-example-{i}"))
+        .build()
+        .expect("build the ECS thread pool");
+
+    App::new(collection).with_thread_pool(pool).run().await;
 }
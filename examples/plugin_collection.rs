@@ -37,7 +37,7 @@ impl<SD: SharedData + AdditionalRequirement> Plugin<SD> for Plugin3 {
     fn apply_pre_update(&mut self, sd: &mut SD) {
         let val = sd.get_val();
 
-        if !(val >= u8::MAX - 1) {
+        if val < u8::MAX - 1 {
             sd.set_val(val + 1);
         } else {
             sd.set_val(0);
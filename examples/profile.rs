@@ -6,6 +6,7 @@ use typed_ecs::{
     plugin::Plugin,
     profile::setup_default_profiling,
     shared_data::SharedData,
+    should_exit::ShouldExit,
 };
 
 struct Sleep200msPlugin;
@@ -43,7 +44,7 @@ impl<SD: SharedData + AdditionalRequirement> Plugin<SD> for Plugin3 {
     fn apply_pre_update(&mut self, sd: &mut SD) {
         let val = sd.get_val();
 
-        if !(val >= u8::MAX - 1) {
+        if val < u8::MAX - 1 {
             sd.set_val(val + 1);
         } else {
             sd.set_val(0);
@@ -79,9 +80,9 @@ impl<SD: SharedData + AdditionalRequirement> Plugin<SD> for CtrlCHandler {
     fn build() -> Self {
         Self
     }
-    fn exit_check(&mut self, should_exit: &mut bool, sd: &SD) {
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &SD) {
         if sd.get_i() >= 100 {
-            *should_exit = true;
+            should_exit.request_exit();
         }
     }
 }
@@ -0,0 +1,78 @@
+use std::{sync::OnceLock, time::Instant};
+
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    profile_lite::{self, Profiler, Slot},
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+};
+
+/// Stands in for a hardware cycle counter, such as the Cortex-M
+/// `DWT::cycle_count`: nanoseconds since startup, wrapping every ~4s.
+fn cycles() -> u32 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u32
+}
+
+// 18 systems per plugin, the default (empty) ones included.
+static SLOTS: [Slot; 64] = [const { Slot::new() }; 64];
+static PROFILER: Profiler = Profiler::new(cycles, &SLOTS);
+
+const FRAMES: u32 = 100;
+
+struct SpinPlugin;
+
+impl<SD: SharedData> Plugin<SD> for SpinPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        std::hint::black_box((0..10_000u64).sum::<u64>());
+    }
+
+    async fn async_update(&mut self, _sd: &SD) {
+        tokio::task::yield_now().await;
+    }
+}
+
+struct ReportPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for ReportPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == FRAMES {
+            should_exit.request_exit();
+        }
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        let profiler = profile_lite::installed().expect("installed in main");
+        let mut dump = String::new();
+        profiler.dump(&mut dump).unwrap();
+        println!("{dump}");
+
+        let update = profiler.get("Update", "SpinPlugin", "update").unwrap();
+        assert_eq!(update.count, FRAMES);
+        assert!(update.min <= update.mean() && update.mean() <= update.max);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    profile_lite::install(&PROFILER);
+
+    generate_collection!(SpinPlugin, ReportPlugin);
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    App::new(collection).run().await;
+
+    assert_eq!(PROFILER.dropped(), 0);
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    replay::{Decoder, Encoder, RecordExecutor, ReplayError, ReplayExecutor},
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+#[derive(Default)]
+struct Thermostat {
+    /// Written by SensorPlugin, the only source of non-determinism.
+    temperature: f32,
+    /// Written by FilterPlugin and HeaterPlugin, from the temperature.
+    smoothed: f32,
+    heating: bool,
+    switches: u32,
+}
+
+/// Only the sensor readings are recorded: everything else follows from
+/// them.
+impl SharedData for Thermostat {
+    fn build() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, out: &mut Encoder) {
+        out.put_f32(self.temperature);
+    }
+
+    fn replay(&mut self, input: &mut Decoder<'_>) -> Result<(), ReplayError> {
+        self.temperature = input.get_f32()?;
+        Ok(())
+    }
+}
+
+/// Noisy readings, then exit after 200 of them.
+struct SensorPlugin {
+    readings: u32,
+}
+
+impl Plugin<Thermostat> for SensorPlugin {
+    fn build() -> Self {
+        Self { readings: 0 }
+    }
+
+    fn apply_pre_update(&mut self, sd: &mut Thermostat) {
+        self.readings += 1;
+        // Only a new reading every 4th frame: the other frames are logged
+        // as unchanged, in a single byte.
+        if self.readings % 4 == 1 {
+            sd.temperature = 19.0 + rand::random::<f32>() * 2.0;
+        }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &Thermostat) {
+        if self.readings == 200 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+struct FilterPlugin;
+
+impl Plugin<Thermostat> for FilterPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Thermostat) {
+        sd.smoothed += (sd.temperature - sd.smoothed) * 0.2;
+    }
+}
+
+struct HeaterPlugin;
+
+impl Plugin<Thermostat> for HeaterPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_post_update(&mut self, sd: &mut Thermostat) {
+        let heating = sd.smoothed < 20.0;
+        if heating != sd.heating {
+            sd.heating = heating;
+            sd.switches += 1;
+        }
+    }
+}
+
+mod recorded {
+    use super::*;
+    generate_collection!(SensorPlugin, FilterPlugin, HeaterPlugin);
+}
+
+mod replayed {
+    use super::*;
+    // Without the sensor.
+    generate_collection!(FilterPlugin, HeaterPlugin);
+}
+
+#[tokio::main]
+async fn main() {
+    let path = std::env::temp_dir().join("typed_ecs_thermostat.replay");
+
+    let log = BufWriter::new(File::create(&path).unwrap());
+    let mut app = App::new_with_executor(
+        recorded::build_generated_collection::<Thermostat>(),
+        RecordExecutor::new(log),
+    );
+    let reason = app.run().await;
+    if let Some(error) = app.executor_mut().take_error() {
+        panic!("{error}");
+    }
+    let recorded = (app.shared_data.smoothed, app.shared_data.switches);
+    println!(
+        "Recorded until {reason:?}: {} bytes, heater switched {} times",
+        std::fs::metadata(&path).unwrap().len(),
+        recorded.1
+    );
+
+    let log = BufReader::new(File::open(&path).unwrap());
+    let mut app = App::new_with_executor(
+        replayed::build_generated_collection::<Thermostat>(),
+        ReplayExecutor::new(log).unwrap(),
+    );
+    app.run().await;
+    if let Some(error) = app.executor_mut().take_error() {
+        panic!("{error}");
+    }
+    let frames = app.executor().frames();
+    println!(
+        "Replayed {frames} frames: heater switched {} times",
+        app.shared_data.switches
+    );
+
+    assert_eq!(frames, 200);
+    assert_eq!(
+        (app.shared_data.smoothed, app.shared_data.switches),
+        recorded
+    );
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,173 @@
+use core::pin::pin;
+
+use serde::{Deserialize, Serialize};
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+    snapshot::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Game {
+    frame: u32,
+    position: (i32, i32),
+    inventory: [Option<Item>; 4],
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+enum Item {
+    Key,
+    Potion(u8),
+}
+
+impl SharedData for Game {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+impl Snapshot for Game {
+    const SCHEMA: &'static str = "square-walk";
+    const VERSION: u32 = 3;
+}
+
+/// Walks in a square, picking up items.
+struct PlayerPlugin {
+    // Private state, saved along the shared data.
+    steps: u32,
+}
+
+impl Plugin<Game> for PlayerPlugin {
+    fn build() -> Self {
+        Self { steps: 0 }
+    }
+
+    fn apply_update(&mut self, sd: &mut Game) {
+        sd.frame += 1;
+        self.steps += 1;
+        let (dx, dy) = [(1, 0), (0, 1), (-1, 0), (0, -1)][(self.steps / 5 % 4) as usize];
+        sd.position.0 += dx;
+        sd.position.1 += dy;
+        if self.steps.is_multiple_of(7) {
+            let item = match self.steps % 2 {
+                0 => Item::Key,
+                _ => Item::Potion(self.steps as u8),
+            };
+            if let Some(slot) = sd.inventory.iter_mut().find(|slot| slot.is_none()) {
+                *slot = Some(item);
+            }
+        }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Game) {
+        if sd.frame == 30 {
+            should_exit.request_exit();
+        }
+    }
+
+    fn save_state(&self, out: &mut SnapshotWriter<'_>) -> Result<(), SnapshotError> {
+        out.put(&self.steps)
+    }
+
+    fn load_state(
+        input: &mut SnapshotReader<'_>,
+    ) -> Result<impl FnOnce(&mut Self) + use<>, SnapshotError> {
+        let steps = input.get()?;
+        Ok(move |plugin: &mut Self| plugin.steps = steps)
+    }
+}
+
+/// A save game, as written by a previous version of the game.
+#[derive(Serialize, Deserialize)]
+struct OldGame {
+    frame: u32,
+}
+
+impl SharedData for OldGame {
+    fn build() -> Self {
+        Self { frame: 0 }
+    }
+}
+
+impl Snapshot for OldGame {
+    const SCHEMA: &'static str = "square-walk";
+    const VERSION: u32 = 2;
+}
+
+struct IdlePlugin;
+
+impl<SD: SharedData> Plugin<SD> for IdlePlugin {
+    fn build() -> Self {
+        Self
+    }
+}
+
+mod game {
+    use super::*;
+    generate_collection!(PlayerPlugin);
+}
+
+mod old_game {
+    use super::*;
+    generate_collection!(IdlePlugin);
+}
+
+mod modded_game {
+    use super::*;
+    generate_collection!(PlayerPlugin, IdlePlugin);
+}
+
+#[tokio::main]
+async fn main() {
+    // Save the game at frame 20, driving the app with the executor
+    // building blocks, for the snapshot to be taken at a frame boundary.
+    let mut app = App::new(game::build_generated_collection::<Game>());
+    let mut save = [0u8; 128];
+    let mut save_len = 0;
+
+    app.run_startup().await;
+    let mut tasks = pin!(app.background_tasks());
+    while !app.run_frame(tasks.as_mut()).await {
+        if app.shared_data.frame == 20 {
+            save_len = app.save_snapshot(&mut save).unwrap().len();
+            println!("Saved frame 20 in {save_len} bytes");
+        }
+    }
+    app.run_shutdown();
+    let end = (app.shared_data.position, app.shared_data.inventory);
+
+    // Load the save into a new app, and play the last 10 frames again.
+    // The startup schedules don't run: the save restores their effects.
+    let mut app = App::new(game::build_generated_collection::<Game>());
+    app.load_snapshot(&save[..save_len]).unwrap();
+    assert_eq!(app.shared_data.frame, 20);
+    println!("Loaded: {:?}", app.shared_data.inventory);
+
+    let mut tasks = pin!(app.background_tasks());
+    while !app.run_frame(tasks.as_mut()).await {}
+    assert_eq!((app.shared_data.position, app.shared_data.inventory), end);
+    println!("Frame 30 reached again, at {:?}", end.0);
+
+    // Saves of an older version are rejected before being decoded.
+    let old = App::new(old_game::build_generated_collection::<OldGame>());
+    let old_save = old.save_snapshot(&mut [0u8; 64]).unwrap().to_vec();
+    let mut app = App::new(game::build_generated_collection::<Game>());
+    let error = app.load_snapshot(&old_save).unwrap_err();
+    println!("Old save: {error}");
+    assert_eq!(
+        error,
+        SnapshotError::Version {
+            expected: 3,
+            found: 2
+        }
+    );
+
+    // As are saves of another collection.
+    let mut app = App::new(modded_game::build_generated_collection::<Game>());
+    let error = app.load_snapshot(&save[..save_len]).unwrap_err();
+    println!("Other plugins: {error}");
+    assert_eq!(error, SnapshotError::Layout);
+}
@@ -0,0 +1,67 @@
+use core::pin::pin;
+
+use typed_ecs::{
+    app::App, macros::generate_collection, plugin::Plugin, shared_data::SharedData,
+    should_exit::ShouldExit, static_app,
+};
+
+/// The state of a board, e.g. read from its peripherals.
+#[derive(Default)]
+struct Board {
+    ticks: u32,
+    led: bool,
+}
+
+impl SharedData for Board {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// Toggles the LED every 4 ticks.
+struct BlinkPlugin;
+
+impl Plugin<Board> for BlinkPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Board) {
+        sd.ticks += 1;
+        if sd.ticks.is_multiple_of(4) {
+            sd.led = !sd.led;
+            println!(
+                "tick {}: LED {}",
+                sd.ticks,
+                if sd.led { "on" } else { "off" }
+            );
+        }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Board) {
+        if sd.ticks == 16 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+generate_collection!(BlinkPlugin);
+
+type BoardApp = App<Board, GeneratedPluginCollection<Board>>;
+
+#[tokio::main]
+async fn main() {
+    // Built once, in place, into a `static`: nothing of the app is on the
+    // stack (but the plugin being built), nor on the heap.
+    let app: &'static mut BoardApp =
+        static_app!(BoardApp => |slot| App::new_in(slot, build_generated_collection_in));
+    println!("App of {} bytes, in a static", size_of::<BoardApp>());
+
+    // On a board, each frame would run from a timer interrupt (or a task
+    // awaiting it); here, from a loop.
+    app.run_startup().await;
+    let mut tasks = pin!(app.background_tasks());
+    while !app.run_frame(tasks.as_mut()).await {}
+    app.run_shutdown();
+    assert_eq!(app.shared_data.ticks, 16);
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::ShouldExit,
+    system_stats::SystemStats,
+};
+
+struct SlowPlugin;
+
+impl<SD: SharedData> Plugin<SD> for SlowPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn update(&mut self, _sd: &SD) {
+        std::thread::sleep(Duration::from_millis(2));
+    }
+
+    async fn async_update(&mut self, _sd: &SD) {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+/// Stands in for a debug overlay: prints the table every 10 frames.
+struct OverlayPlugin {
+    frames: u32,
+}
+
+impl<SD: SharedData> Plugin<SD> for OverlayPlugin {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        if self.frames == 30 {
+            should_exit.request_exit();
+        }
+    }
+
+    fn on_system_stats(&mut self, stats: &SystemStats<'_>, _sd: &SD) {
+        self.frames += 1;
+        if !self.frames.is_multiple_of(10) {
+            return;
+        }
+
+        println!("--- frame {} ---", self.frames);
+        for (schedule, timing) in stats.schedules().filter(|(_, t)| t.count > 0) {
+            println!(
+                "{schedule:<24} last {:>10?} avg {:>10?} worst {:>10?}",
+                timing.last, timing.average, timing.worst
+            );
+        }
+
+        let update = stats.system("Update", "SlowPlugin").unwrap();
+        assert_eq!(update.count, self.frames as u64);
+        assert!(update.worst >= Duration::from_millis(2));
+        let update_schedule = stats.schedule("Update").unwrap();
+        assert!(update_schedule.worst >= update.worst);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    generate_collection!(SlowPlugin, OverlayPlugin);
+    let collection: GeneratedPluginCollection<PhantomSharedData> = build_generated_collection();
+    let mut app = App::new(collection);
+    app.run().await;
+
+    let stats = app.system_stats();
+    // 30 frames, and the one the app exited on.
+    assert_eq!(stats.system("Update", "SlowPlugin").unwrap().count, 31);
+    let slowest = stats
+        .systems()
+        .max_by_key(|entry| entry.stats.worst)
+        .unwrap();
+    println!(
+        "Slowest system: {}::{} ({:?})",
+        slowest.plugin, slowest.system, slowest.stats.worst
+    );
+    assert_eq!(slowest.plugin, "SlowPlugin");
+}
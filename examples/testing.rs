@@ -0,0 +1,83 @@
+use typed_ecs::{
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::{ExitReason, ShouldExit},
+    testing::TestApp,
+};
+
+/// Test SharedData: a countdown, and what the plugins saw of it.
+struct Countdown {
+    remaining: u32,
+    launched: bool,
+}
+
+impl SharedData for Countdown {
+    fn build() -> Self {
+        Self {
+            remaining: 3,
+            launched: false,
+        }
+    }
+}
+
+struct CountdownPlugin {
+    ticks: u32,
+}
+
+impl Plugin<Countdown> for CountdownPlugin {
+    fn build() -> Self {
+        Self { ticks: 0 }
+    }
+
+    fn apply_update(&mut self, sd: &mut Countdown) {
+        self.ticks += 1;
+        sd.remaining = sd.remaining.saturating_sub(1);
+    }
+
+    async fn async_update(&mut self, sd: &Countdown) {
+        // Awaited by the harness' own executor.
+        futures::future::ready(sd.remaining).await;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Countdown) {
+        if sd.remaining == 0 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+struct LaunchPlugin;
+
+impl Plugin<Countdown> for LaunchPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn on_exit(&mut self, _sd: &Countdown) {
+        println!("Launch!");
+    }
+
+    fn apply_async_update(&mut self, sd: &mut Countdown) {
+        sd.launched = sd.remaining == 0;
+    }
+}
+
+fn main() {
+    generate_collection!(CountdownPlugin, LaunchPlugin);
+    let mut test = TestApp::new(build_generated_collection::<Countdown>());
+
+    assert_eq!(test.tick(), None);
+    assert_eq!(test.shared_data().remaining, 2);
+    assert_eq!(test.collection().countdownplugin.ticks, 1);
+
+    // Between frames, the state can be changed as well.
+    test.shared_data_mut().remaining = 1;
+    assert_eq!(test.run(10), Some(ExitReason::Requested));
+    assert_eq!(test.frame(), 2);
+    // The last frame stopped after ExitCheck.
+    assert!(!test.shared_data().launched);
+
+    test.shutdown();
+    println!("Exited after {} frames", test.frame());
+}
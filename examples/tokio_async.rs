@@ -46,6 +46,30 @@ impl<SD: SharedData + CounterMemory> Plugin<SD> for ExitCounterPlugin {
     }
 }
 
+/// Async systems also exist for the pre-update and post-update phases,
+/// so IO can be awaited right where its result is needed in the frame.
+struct FramePositionPlugin;
+
+impl<SD: SharedData + CounterMemory> Plugin<SD> for FramePositionPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    async fn async_pre_update(&mut self, sd: &SD) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        println!("Frame {}: async_pre_update, before update", sd.get_i());
+    }
+
+    async fn async_post_update(&mut self, sd: &SD) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        println!("Frame {}: async_post_update, before exit_check", sd.get_i());
+    }
+
+    async fn async_update(&mut self, sd: &SD) {
+        println!("Frame {}: async_update, end of the frame", sd.get_i());
+    }
+}
+
 seq!(N in 1..=50 {
     struct Plugin~N;
 
@@ -65,7 +89,7 @@ async fn main() {
     typed_ecs::profile::setup_default_profiling();
 
     seq!(N in 1..=50 {
-        generate_collection!(#(Plugin~N,)* ExitCounterPlugin);
+        generate_collection!(#(Plugin~N,)* ExitCounterPlugin, FramePositionPlugin);
     });
 
     let collection: GeneratedPluginCollection<SDimpl> = build_generated_collection();
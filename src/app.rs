@@ -1,52 +1,467 @@
-use core::marker::PhantomData;
-
-#[cfg(feature = "profile")]
-use tracing::trace;
+use core::{
+    future::poll_fn,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::ControlFlow,
+    pin::Pin,
+    ptr,
+    task::Poll,
+};
 
 use crate::executor::{DefaultExecutor, ExecutorTrait};
+use crate::instrumentation::{DefaultInstrumentation, Instrumentation};
 
-use crate::{plugin_collection::PluginCollection, shared_data::SharedData};
+use crate::{
+    background::{CollectionTasks, alongside},
+    plugin_collection::PluginCollection,
+    shared_data::SharedData,
+    should_exit::{ExitReason, ShouldExit},
+};
 
-pub struct App<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait = DefaultExecutor>
-{
-    pub executor: PhantomData<Executor>,
+pub struct App<
+    SD: SharedData,
+    PC: PluginCollection<SD>,
+    Executor: ExecutorTrait = DefaultExecutor,
+    I: Instrumentation = DefaultInstrumentation,
+> {
+    // Taken by `App::run` while it runs the app.
+    executor: Option<Executor>,
     pub shared_data: SD,
     pub plugin_collection: PC,
+    /// Hooks called around every schedule and system, see
+    /// [`App::with_instrumentation`].
+    pub instrumentation: I,
+    exit_hooks_ran: bool,
 }
 
 impl<SD: SharedData, PC: PluginCollection<SD>> App<SD, PC, DefaultExecutor> {
     pub fn new(plugin_collection: PC) -> App<SD, PC, DefaultExecutor> {
         App::<SD, PC, DefaultExecutor> {
-            executor: PhantomData::<DefaultExecutor>,
+            executor: Some(DefaultExecutor),
             shared_data: SD::build(),
             plugin_collection,
+            instrumentation: DefaultInstrumentation::default(),
+            exit_hooks_ran: false,
+        }
+    }
+
+    /// Builds the app into `slot`, and its plugin collection with `build`
+    /// (e.g. `build_generated_collection_in`): unlike [`App::new`], the
+    /// collection is never on the stack, for an app in a `static` (see
+    /// `static_app`).
+    ///
+    /// # Panics
+    ///
+    /// If `build` returns another collection than the one of its slot.
+    pub fn new_in(
+        slot: &mut MaybeUninit<Self>,
+        build: impl FnOnce(&mut MaybeUninit<PC>) -> &mut PC,
+    ) -> &mut Self {
+        let app = slot.as_mut_ptr();
+        // SAFETY: every field is written before the slot is assumed
+        // initialized, the collection by `build`, checked to have returned
+        // its slot.
+        unsafe {
+            let collection = &raw mut (*app).plugin_collection;
+            let built = build(&mut *collection.cast::<MaybeUninit<PC>>());
+            assert!(
+                ptr::eq(built, collection),
+                "plugin collection built out of its slot"
+            );
+            (&raw mut (*app).executor).write(Some(DefaultExecutor));
+            (&raw mut (*app).shared_data).write(SD::build());
+            (&raw mut (*app).instrumentation).write(DefaultInstrumentation::default());
+            (&raw mut (*app).exit_hooks_ran).write(false);
+            &mut *app
         }
     }
 }
 
 impl<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait> App<SD, PC, Executor> {
-    pub fn new_with_executor(plugin_collection: PC, executor: PhantomData<Executor>) -> Self {
+    /// An app run by `executor` (see [`App::run`]), e.g.
+    /// `IdleExecutor::new(wait)`.
+    pub fn new_with_executor(plugin_collection: PC, executor: Executor) -> Self {
         Self {
-            executor,
+            executor: Some(executor),
             shared_data: SD::build(),
             plugin_collection,
+            instrumentation: DefaultInstrumentation::default(),
+            exit_hooks_ran: false,
+        }
+    }
+}
+
+impl<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait, I: Instrumentation>
+    App<SD, PC, Executor, I>
+{
+    /// Replaces the hooks called around every schedule and system
+    /// (by default, the ones the `profile`/`profile-lite` features
+    /// select), e.g. with a custom tracer, counters or a logger.
+    pub fn with_instrumentation<I2: Instrumentation>(
+        self,
+        instrumentation: I2,
+    ) -> App<SD, PC, Executor, I2> {
+        // The app implements `Drop`: its fields are moved out of it, and
+        // its instrumentation dropped in place, as it's never used, nor
+        // dropped, again.
+        let mut app = ManuallyDrop::new(self);
+        // SAFETY: see above, every field is read or dropped exactly once.
+        unsafe {
+            ptr::drop_in_place(&mut app.instrumentation);
+            App {
+                executor: ptr::read(&app.executor),
+                shared_data: ptr::read(&app.shared_data),
+                plugin_collection: ptr::read(&app.plugin_collection),
+                instrumentation,
+                exit_hooks_ran: app.exit_hooks_ran,
+            }
+        }
+    }
+
+    /// Runs the parallel schedules on `fork_join` instead of the backend
+    /// the collection has been built with.
+    #[cfg(feature = "fork-join")]
+    pub fn with_fork_join(mut self, fork_join: PC::ForkJoin) -> Self {
+        self.plugin_collection.set_fork_join(fork_join);
+        self
+    }
+
+    /// Runs the parallel schedules on `pool` instead of rayon's global
+    /// thread pool. Accepts an `EcsThreadPool` (e.g. built with
+    /// `ThreadPoolConfig::build`) or a caller-supplied `rayon::ThreadPool`.
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(self, pool: impl Into<crate::thread_pool::EcsThreadPool>) -> Self
+    where
+        PC: PluginCollection<SD, ForkJoin = crate::thread_pool::EcsThreadPool>,
+    {
+        self.with_fork_join(pool.into())
+    }
+
+    // ------------------------------------------------------------
+    // BUILDING BLOCKS OF AN EXECUTOR, IN THEIR ORDER OF EXECUTION.
+    // `ExecutorTrait::run` is made of them, so a custom executor can
+    // reuse them instead of rewriting the schedule order.
+    // ------------------------------------------------------------
+
+    /// Runs the startup schedules, in order: Startup, ApplyStartup,
+    /// AsyncStartup and ApplyAsyncStartup.
+    pub async fn run_startup(&mut self) {
+        self.run_startup_with(|_, _| {}).await;
+    }
+
+    /// [`App::run_startup`], calling `after_apply` with the name of each
+    /// apply schedule (e.g. `"ApplyStartup"`) once it ran, while the shared
+    /// data can still be observed or overwritten before the next schedule.
+    pub async fn run_startup_with(&mut self, mut after_apply: impl FnMut(&'static str, &mut SD)) {
+        self.plugin_collection
+            .startup_all(&self.shared_data, &self.instrumentation);
+        self.plugin_collection
+            .apply_startup_all(&mut self.shared_data, &self.instrumentation);
+        after_apply("ApplyStartup", &mut self.shared_data);
+
+        self.plugin_collection
+            .async_startup_all(&self.shared_data, &self.instrumentation)
+            .await;
+        self.plugin_collection
+            .apply_async_startup_all(&mut self.shared_data, &self.instrumentation);
+        after_apply("ApplyAsyncStartup", &mut self.shared_data);
+    }
+
+    /// Creates the background tasks of the plugins, and the slots of their
+    /// async tasks (see `background`). They belong to this run of the
+    /// app: the returned value must be pinned, and given to every
+    /// [`App::run_frame`] call. Without the `background-tasks` feature,
+    /// there are none.
+    pub fn background_tasks(&mut self) -> impl CollectionTasks<SD, PC> + use<SD, PC, Executor, I> {
+        self.plugin_collection.background_tasks_all()
+    }
+
+    /// Runs one iteration of the loop, while polling the background
+    /// `tasks`: PreUpdate, AsyncPreUpdate, Update, PostUpdate and
+    /// AsyncPostUpdate, each followed by its apply schedule, then ExitCheck
+    /// and, unless a plugin requested exit, AsyncUpdate and
+    /// ApplyAsyncUpdate. Returns whether a plugin requested exit.
+    ///
+    /// The tasks are polled once at the start of the frame, then while the
+    /// async schedules await. The async tasks are started in AsyncUpdate,
+    /// and the results of all of them applied right before
+    /// ApplyAsyncUpdate.
+    pub async fn run_frame(&mut self, tasks: Pin<&mut dyn CollectionTasks<SD, PC>>) -> bool {
+        self.run_frame_with(tasks, |_, _| ControlFlow::Continue(()))
+            .await
+    }
+
+    /// [`App::run_frame`], calling `after_apply` with the name of each
+    /// apply schedule (e.g. `"ApplyUpdate"`) once it ran. A `Break` ends
+    /// the app as an exit request does, once the frame is over, and makes
+    /// this return true.
+    pub async fn run_frame_with(
+        &mut self,
+        mut tasks: Pin<&mut dyn CollectionTasks<SD, PC>>,
+        mut after_apply: impl FnMut(&'static str, &mut SD) -> ControlFlow<()>,
+    ) -> bool {
+        let mut should_exit = false;
+        let mut stop = false;
+
+        poll_fn(|cx| {
+            let _ = tasks.as_mut().poll_tasks(cx);
+            Poll::Ready(())
+        })
+        .await;
+
+        self.plugin_collection
+            .pre_update_all(&self.shared_data, &self.instrumentation);
+        self.plugin_collection
+            .apply_pre_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyPreUpdate", &mut self.shared_data).is_break();
+
+        alongside(
+            self.plugin_collection
+                .async_pre_update_all(&self.shared_data, &self.instrumentation),
+            tasks.as_mut(),
+        )
+        .await;
+        self.plugin_collection
+            .apply_async_pre_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyAsyncPreUpdate", &mut self.shared_data).is_break();
+
+        self.plugin_collection
+            .update_all(&self.shared_data, &self.instrumentation);
+        self.plugin_collection
+            .apply_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyUpdate", &mut self.shared_data).is_break();
+
+        self.plugin_collection
+            .post_update_all(&self.shared_data, &self.instrumentation);
+        self.plugin_collection
+            .apply_post_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyPostUpdate", &mut self.shared_data).is_break();
+
+        alongside(
+            self.plugin_collection
+                .async_post_update_all(&self.shared_data, &self.instrumentation),
+            tasks.as_mut(),
+        )
+        .await;
+        self.plugin_collection
+            .apply_async_post_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyAsyncPostUpdate", &mut self.shared_data).is_break();
+
+        self.plugin_collection.exit_check_all(
+            &mut should_exit,
+            &self.shared_data,
+            &self.instrumentation,
+        );
+
+        // AsyncUpdate is skipped on the last frame, which is reported
+        // nonetheless.
+        if should_exit.is_true() || stop {
+            #[cfg(feature = "system-stats")]
+            self.plugin_collection.system_stats_all(&self.shared_data);
+            return true;
+        }
+
+        tasks
+            .as_mut()
+            .start(&mut self.plugin_collection, &self.shared_data);
+        self.plugin_collection
+            .async_update_all(&self.shared_data, &self.instrumentation, tasks.as_mut())
+            .await;
+        tasks
+            .as_mut()
+            .apply(&mut self.plugin_collection, &mut self.shared_data);
+        self.plugin_collection
+            .apply_async_update_all(&mut self.shared_data, &self.instrumentation);
+        stop |= after_apply("ApplyAsyncUpdate", &mut self.shared_data).is_break();
+
+        #[cfg(feature = "system-stats")]
+        self.plugin_collection.system_stats_all(&self.shared_data);
+
+        stop
+    }
+
+    /// The executor of the app, e.g. to read what it gathered once
+    /// `App::run` returned.
+    ///
+    /// # Panics
+    ///
+    /// If called by the executor itself, while it runs the app.
+    pub fn executor(&self) -> &Executor {
+        self.executor
+            .as_ref()
+            .expect("executor taken by a running App::run")
+    }
+
+    /// See [`App::executor`].
+    pub fn executor_mut(&mut self) -> &mut Executor {
+        self.executor
+            .as_mut()
+            .expect("executor taken by a running App::run")
+    }
+
+    /// Plugins of the collection, their systems, and how the schedules run
+    /// them: see `introspection`.
+    pub fn collection_info(&self) -> crate::introspection::CollectionInfo {
+        PC::INFO
+    }
+
+    /// Whether the plugins have work for the next frame (see
+    /// `idle::IdleExecutor`).
+    pub fn idle(&self) -> crate::idle::Idle {
+        self.plugin_collection.idle_all(&self.shared_data)
+    }
+
+    /// Number of time budgets exceeded so far (see `budget::Watchdog`).
+    #[cfg(feature = "budget")]
+    pub fn budget_violations(&self) -> u32 {
+        self.plugin_collection.budget_violations()
+    }
+
+    /// Report of the first panic of a plugin since the last call, whatever
+    /// its `OnPanic` policy: a rebuilt plugin panicked too.
+    #[cfg(feature = "panic-isolation")]
+    pub fn take_panic(&mut self) -> Option<crate::panic_isolation::PanicReport> {
+        self.plugin_collection.take_panic()
+    }
+
+    /// Timing statistics of the plugins systems and of the schedules.
+    #[cfg(feature = "system-stats")]
+    pub fn system_stats(&self) -> crate::system_stats::SystemStats<'_> {
+        self.plugin_collection.system_stats()
+    }
+
+    /// Writes a snapshot of the shared data and of the plugins state (see
+    /// `Plugin::save_state`) into `buf`, and returns the bytes written.
+    /// Call it at a frame boundary: between `App::run_frame` calls, or
+    /// once `App::run` returned.
+    #[cfg(feature = "serde")]
+    pub fn save_snapshot<'b>(
+        &self,
+        buf: &'b mut [u8],
+    ) -> Result<&'b mut [u8], crate::snapshot::SnapshotError>
+    where
+        SD: crate::snapshot::Snapshot,
+    {
+        use crate::snapshot::{SnapshotWriter, write_header};
+
+        let mut out = SnapshotWriter::new(buf);
+        write_header::<SD>(&mut out, PC::SNAPSHOT_FINGERPRINT)?;
+        out.section(|out| out.put(&self.shared_data))?;
+        self.plugin_collection.save_state_all(&mut out)?;
+        Ok(out.finish())
+    }
+
+    /// Restores a snapshot written by [`App::save_snapshot`]. The header
+    /// and the length of every section are checked, and everything is
+    /// decoded, before anything is restored: a snapshot of another
+    /// `Snapshot::VERSION`, of another collection, or a corrupt one, leaves
+    /// the app untouched.
+    #[cfg(feature = "serde")]
+    pub fn load_snapshot(&mut self, bytes: &[u8]) -> Result<(), crate::snapshot::SnapshotError>
+    where
+        SD: crate::snapshot::Snapshot,
+    {
+        use crate::snapshot::{SnapshotReader, read_header};
+
+        let mut input = SnapshotReader::new(bytes);
+        read_header::<SD>(&mut input, PC::SNAPSHOT_FINGERPRINT)?;
+        self.plugin_collection
+            .load_state_all(&mut input, &mut self.shared_data)
+    }
+
+    /// Why the exit has been requested, once `run_frame` returned true.
+    pub fn exit_reason(&mut self) -> ExitReason {
+        #[cfg(feature = "panic-isolation")]
+        if let Some(report) = self.plugin_collection.take_exit_panic() {
+            return ExitReason::PluginPanicked(report);
+        }
+        #[cfg(feature = "budget")]
+        if let Some(violation) = self.plugin_collection.take_budget_violation() {
+            return ExitReason::BudgetExceeded(violation);
+        }
+        ExitReason::Requested
+    }
+
+    /// Runs the OnExit schedule. It only ever runs once per app: later
+    /// calls do nothing.
+    pub fn run_shutdown(&mut self) {
+        if self.exit_hooks_ran {
+            return;
         }
+        self.exit_hooks_ran = true;
+
+        #[cfg(feature = "profile")]
+        let _guard = tracing::info_span!("Executor OnExit hooks").entered();
+        self.plugin_collection
+            .on_exit_all(&self.shared_data, &self.instrumentation);
     }
 
-    pub async fn run(&mut self) {
+    /// Runs the app with its executor, then hands the exit reason to
+    /// `ExecutorTrait::run_exit_hooks`, which runs the OnExit schedule by
+    /// default.
+    ///
+    /// If the run is interrupted (a system panics, or this future gets
+    /// dropped), the exit hooks are still invoked, with
+    /// `ExitReason::Interrupted`. So are they when an app whose OnExit
+    /// schedule didn't run is dropped: never run, or run by an executor
+    /// that skipped it.
+    pub async fn run(&mut self) -> ExitReason {
         #[cfg(feature = "profile")]
         let _guard = tracing::info_span!("Executor Runtime").entered();
-        let mut executor = Executor::init();
-        executor.run(self).await;
+        let mut run = RunGuard {
+            executor: self.executor.take(),
+            app: self,
+            armed: true,
+        };
+        let executor = run
+            .executor
+            .as_mut()
+            .expect("executor lost by a previous run of the app");
+        let reason = executor.run(&mut *run.app).await;
+        run.armed = false;
+        executor.run_exit_hooks(run.app, &reason);
+        reason
+    }
+}
+
+/// Invokes the exit hooks if `App::run` is interrupted by a panic or a
+/// cancellation, then gives the executor back to the app.
+struct RunGuard<
+    'a,
+    SD: SharedData,
+    PC: PluginCollection<SD>,
+    Executor: ExecutorTrait,
+    I: Instrumentation,
+> {
+    executor: Option<Executor>,
+    app: &'a mut App<SD, PC, Executor, I>,
+    armed: bool,
+}
+
+impl<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait, I: Instrumentation> Drop
+    for RunGuard<'_, SD, PC, Executor, I>
+{
+    fn drop(&mut self) {
+        if let Some(mut executor) = self.executor.take() {
+            if self.armed {
+                executor.run_exit_hooks(self.app, &ExitReason::Interrupted);
+            }
+            self.app.executor = Some(executor);
+        }
     }
 }
 
-impl<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait> Drop
-    for App<SD, PC, Executor>
+/// Invokes the exit hooks of an app dropped before its OnExit schedule
+/// ran, with `ExitReason::Interrupted`.
+impl<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait, I: Instrumentation> Drop
+    for App<SD, PC, Executor, I>
 {
     fn drop(&mut self) {
-        #[cfg(feature = "profile")]
-        let _guard = tracing::info_span!("Executor OnExit hooks").entered();
-        self.plugin_collection.on_exit_all(&self.shared_data);
+        if self.exit_hooks_ran {
+            return;
+        }
+        match self.executor.take() {
+            Some(mut executor) => executor.run_exit_hooks(self, &ExitReason::Interrupted),
+            None => self.run_shutdown(),
+        }
     }
 }
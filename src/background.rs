@@ -0,0 +1,592 @@
+//! Plugin work that outlives a frame, with the `background-tasks` feature.
+//!
+//! - The background task of a plugin (`Plugin::background_task`) is created
+//!   once, after the startup schedules, and runs as long as the app does.
+//! - Its async task (`Plugin::async_task`) is started in the AsyncUpdate
+//!   schedule, when none is pending, and may be carried over to the next
+//!   frames (see `timeout::OnTimeout::CarryOver`, `async-timeout`
+//!   feature).
+//!
+//! Neither can borrow the plugin nor the shared data: their results are
+//! closures, applied to both right before the ApplyAsyncUpdate schedule.
+//! They are owned by the [`CollectionTasks`] of one run of an app, so two
+//! apps never share them.
+
+use core::{
+    cell::UnsafeCell,
+    future::{Future, poll_fn},
+    mem::MaybeUninit,
+    pin::{Pin, pin},
+    task::{Context, Poll},
+};
+
+use futures::task::AtomicWaker;
+use portable_atomic::{AtomicU8, Ordering};
+
+// Tasks of the plugins.
+#[cfg(feature = "background-tasks")]
+use {
+    crate::{plugin::Plugin, shared_data::SharedData},
+    futures::{
+        FutureExt,
+        future::FusedFuture,
+        stream::{Fuse, FusedStream, Stream, StreamExt},
+    },
+};
+// Deadlines of the async tasks.
+#[cfg(feature = "async-timeout")]
+use {crate::timeout::TimeoutReport, core::time::Duration};
+#[cfg(all(feature = "background-tasks", feature = "async-timeout"))]
+use {
+    crate::timeout::{OnTimeout, Timer},
+    futures::future::{Either, select},
+};
+
+/// The background and async tasks of the plugins of an app, for one run.
+/// Created by `App::background_tasks`, pinned by the executor, and given
+/// to every `App::run_frame` call.
+pub trait Tasks {
+    /// Polls the background tasks, and the pending async tasks. Ready
+    /// once one of them has a result waiting to be applied.
+    fn poll_tasks(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()>;
+}
+
+/// The frame side of [`Tasks`], implemented for the plugins of a
+/// collection `PC` by `generate_collection!`.
+pub trait CollectionTasks<SD, PC: ?Sized>: Tasks {
+    /// Starts the async task of every plugin which has none pending (nor
+    /// a result waiting), and sets the deadline of all of them.
+    fn start(self: Pin<&mut Self>, collection: &mut PC, sd: &SD);
+
+    /// Polls the tasks, like [`Tasks::poll_tasks`]. Ready once no async
+    /// task is pending within its deadline.
+    fn poll_waiting(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()>;
+
+    /// The nearest deadline of the async tasks still waited for.
+    #[cfg(feature = "async-timeout")]
+    fn next_deadline(self: Pin<&mut Self>) -> Option<Duration>;
+
+    /// Gives up waiting for the async tasks whose deadline is `elapsed`,
+    /// reporting each of them.
+    #[cfg(feature = "async-timeout")]
+    fn expire(self: Pin<&mut Self>, elapsed: Duration, report: &dyn Fn(&TimeoutReport));
+
+    /// Applies the results of the tasks to their plugins and to the
+    /// shared data, after calling `Plugin::on_async_timeout` for the async
+    /// tasks which exceeded their deadline.
+    fn apply(self: Pin<&mut Self>, collection: &mut PC, sd: &mut SD);
+}
+
+/// Awaits `future`, polling the background tasks of the collection every
+/// time it is polled itself.
+///
+/// The frame wraps the AsyncPreUpdate and AsyncPostUpdate schedules with
+/// it (AsyncUpdate polls the tasks in `wait_tasks`, or with it without the
+/// `background-tasks` feature), so the tasks make progress while the frame
+/// awaits.
+pub async fn alongside<F: Future, T: ?Sized + Tasks>(
+    future: F,
+    mut tasks: Pin<&mut T>,
+) -> F::Output {
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        let _ = tasks.as_mut().poll_tasks(cx);
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Awaits `future` (the async systems of the AsyncUpdate schedule) and the
+/// async tasks `start` set a deadline to, each until it completes or its
+/// deadline is exceeded, polling the background tasks meanwhile.
+///
+/// Called by the generated collection. The deadlines all start with the
+/// schedule, so a single `timer` sleep is pending at a time, until the
+/// nearest one.
+#[cfg(all(feature = "background-tasks", feature = "async-timeout"))]
+pub async fn wait_tasks<F, SD, PC, T>(
+    future: F,
+    mut tasks: Pin<&mut dyn CollectionTasks<SD, PC>>,
+    timer: &T,
+) where
+    F: Future<Output = ()>,
+    PC: ?Sized,
+    T: Timer,
+{
+    let mut future = pin!(future.fuse());
+    let mut elapsed = Duration::ZERO;
+    loop {
+        let deadline = tasks.as_mut().next_deadline();
+        let waiting = waiting(future.as_mut(), tasks.as_mut());
+        let Some(deadline) = deadline else {
+            waiting.await;
+            return;
+        };
+        let sleep = timer.sleep(deadline.saturating_sub(elapsed));
+        if let Either::Left(_) = select(pin!(waiting), pin!(sleep)).await {
+            return;
+        }
+        elapsed = deadline;
+        tasks
+            .as_mut()
+            .expire(elapsed, &|report| timer.on_timeout(report));
+    }
+}
+
+/// Awaits `future` (the async systems of the AsyncUpdate schedule) and the
+/// async tasks `start` started, polling the background tasks meanwhile.
+///
+/// Called by the generated collection.
+#[cfg(all(feature = "background-tasks", not(feature = "async-timeout")))]
+pub async fn wait_tasks<F, SD, PC>(future: F, tasks: Pin<&mut dyn CollectionTasks<SD, PC>>)
+where
+    F: Future<Output = ()>,
+    PC: ?Sized,
+{
+    waiting(pin!(future.fuse()), tasks).await
+}
+
+/// Ready once `future` completed, and no async task is waited for.
+#[cfg(feature = "background-tasks")]
+fn waiting<'a, F, SD, PC>(
+    mut future: Pin<&'a mut F>,
+    mut tasks: Pin<&'a mut dyn CollectionTasks<SD, PC>>,
+) -> impl Future<Output = ()> + 'a
+where
+    F: FusedFuture<Output = ()>,
+    PC: ?Sized,
+{
+    poll_fn(move |cx| {
+        if !future.is_terminated() {
+            let _ = future.as_mut().poll(cx);
+        }
+        let waited = tasks.as_mut().poll_waiting(cx).is_ready();
+        if waited && future.is_terminated() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+}
+
+/// The tasks of one plugin.
+#[cfg(feature = "background-tasks")]
+#[doc(hidden)]
+pub struct PluginTasks<P, PC: ?Sized, S, B: Stream, K: Future> {
+    plugin: fn(&mut PC) -> &mut P,
+    // Reported once the deadline of the async task is exceeded.
+    #[cfg(feature = "async-timeout")]
+    name: &'static str,
+    // `Plugin::async_task`, naming the type of its future.
+    start: S,
+    background: Fuse<B>,
+    received: Option<B::Item>,
+    task: Option<K>,
+    done: Option<K::Output>,
+    // The async task is pending, and its deadline not exceeded yet.
+    waiting: bool,
+    #[cfg(feature = "async-timeout")]
+    timed_out: bool,
+}
+
+/// Creates the tasks of the plugin `plugin` returns, named `name` (with
+/// the `async-timeout` feature). The generated collection calls it for
+/// each of its plugins.
+#[cfg(feature = "background-tasks")]
+#[doc(hidden)]
+pub fn plugin_tasks<SD: SharedData, P: Plugin<SD>, PC: ?Sized>(
+    plugin: fn(&mut PC) -> &mut P,
+    #[cfg(feature = "async-timeout")] name: &'static str,
+    collection: &mut PC,
+) -> impl CollectionTasks<SD, PC> + use<SD, P, PC> {
+    PluginTasks {
+        plugin,
+        #[cfg(feature = "async-timeout")]
+        name,
+        start: P::async_task,
+        background: plugin(collection).background_task().fuse(),
+        received: None,
+        task: None,
+        done: None,
+        waiting: false,
+        #[cfg(feature = "async-timeout")]
+        timed_out: false,
+    }
+}
+
+/// Fields of a pinned [`PluginTasks`].
+#[cfg(feature = "background-tasks")]
+struct Projection<'a, P, PC: ?Sized, S, B: Stream, K: Future> {
+    plugin: fn(&mut PC) -> &mut P,
+    #[cfg(feature = "async-timeout")]
+    name: &'static str,
+    start: &'a S,
+    background: Pin<&'a mut Fuse<B>>,
+    received: &'a mut Option<B::Item>,
+    task: Pin<&'a mut Option<K>>,
+    done: &'a mut Option<K::Output>,
+    waiting: &'a mut bool,
+    #[cfg(feature = "async-timeout")]
+    timed_out: &'a mut bool,
+}
+
+#[cfg(feature = "background-tasks")]
+impl<P, PC: ?Sized, S, B: Stream, K: Future> PluginTasks<P, PC, S, B, K> {
+    fn project(self: Pin<&mut Self>) -> Projection<'_, P, PC, S, B, K> {
+        // SAFETY: `background` and `task` are never moved out of a pinned
+        // `PluginTasks` (the task is dropped in place, by `Pin::set`), and
+        // the struct doesn't implement `Drop`.
+        unsafe {
+            let this = self.get_unchecked_mut();
+            Projection {
+                plugin: this.plugin,
+                #[cfg(feature = "async-timeout")]
+                name: this.name,
+                start: &this.start,
+                background: Pin::new_unchecked(&mut this.background),
+                received: &mut this.received,
+                task: Pin::new_unchecked(&mut this.task),
+                done: &mut this.done,
+                waiting: &mut this.waiting,
+                #[cfg(feature = "async-timeout")]
+                timed_out: &mut this.timed_out,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "background-tasks")]
+impl<P, PC: ?Sized, S, B: Stream, K: Future> Tasks for PluginTasks<P, PC, S, B, K> {
+    /// Buffers the next result of the background task (once the previous
+    /// one has been applied), and the one of the async task once it
+    /// completes.
+    fn poll_tasks(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut this = self.project();
+        if this.received.is_none()
+            && !this.background.is_terminated()
+            && let Poll::Ready(Some(item)) = this.background.as_mut().poll_next(cx)
+        {
+            *this.received = Some(item);
+        }
+        if let Some(task) = this.task.as_mut().as_pin_mut()
+            && let Poll::Ready(output) = task.poll(cx)
+        {
+            this.task.set(None);
+            *this.done = Some(output);
+            *this.waiting = false;
+        }
+        if this.received.is_some() || this.done.is_some() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "background-tasks")]
+impl<SD, P, PC, S, B, K, F, G> CollectionTasks<SD, PC> for PluginTasks<P, PC, S, B, K>
+where
+    SD: SharedData,
+    P: Plugin<SD>,
+    PC: ?Sized,
+    S: Fn(&mut P, &SD) -> Option<K>,
+    B: Stream<Item = F>,
+    F: FnOnce(&mut P, &mut SD),
+    K: Future<Output = G>,
+    G: FnOnce(&mut P, &mut SD),
+{
+    fn start(self: Pin<&mut Self>, collection: &mut PC, sd: &SD) {
+        let mut this = self.project();
+        if this.task.is_none()
+            && this.done.is_none()
+            && let Some(task) = (this.start)((this.plugin)(collection), sd)
+        {
+            this.task.set(Some(task));
+        }
+        *this.waiting = this.task.is_some();
+    }
+
+    fn poll_waiting(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let _ = self.as_mut().poll_tasks(cx);
+        if self.waiting {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    #[cfg(feature = "async-timeout")]
+    fn next_deadline(self: Pin<&mut Self>) -> Option<Duration> {
+        if self.waiting { P::ASYNC_TIMEOUT } else { None }
+    }
+
+    #[cfg(feature = "async-timeout")]
+    fn expire(self: Pin<&mut Self>, elapsed: Duration, report: &dyn Fn(&TimeoutReport)) {
+        let mut this = self.project();
+        let Some(timeout) = P::ASYNC_TIMEOUT else {
+            return;
+        };
+        if !*this.waiting || timeout > elapsed {
+            return;
+        }
+        report(&TimeoutReport {
+            schedule: "AsyncUpdate",
+            plugin: this.name,
+            system: "async_task",
+            timeout,
+        });
+        *this.waiting = false;
+        *this.timed_out = true;
+        if P::ON_ASYNC_TIMEOUT == OnTimeout::Cancel {
+            this.task.set(None);
+        }
+    }
+
+    fn apply(self: Pin<&mut Self>, collection: &mut PC, sd: &mut SD) {
+        let this = self.project();
+        let plugin = (this.plugin)(collection);
+        #[cfg(feature = "async-timeout")]
+        if core::mem::take(this.timed_out) {
+            plugin.on_async_timeout("async_task", sd);
+        }
+        if let Some(done) = this.done.take() {
+            done(plugin, sd);
+        }
+        if let Some(received) = this.received.take() {
+            received(plugin, sd);
+        }
+    }
+}
+
+/// Two halves of the tasks of a collection: the generated collection
+/// builds a balanced tree of them.
+#[cfg(feature = "background-tasks")]
+#[doc(hidden)]
+pub struct Pair<A, B>(A, B);
+
+#[cfg(feature = "background-tasks")]
+impl<A, B> Pair<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self(a, b)
+    }
+
+    fn project(self: Pin<&mut Self>) -> (Pin<&mut A>, Pin<&mut B>) {
+        // SAFETY: both halves are structurally pinned, and `Pair` doesn't
+        // implement `Drop`.
+        unsafe {
+            let this = self.get_unchecked_mut();
+            (
+                Pin::new_unchecked(&mut this.0),
+                Pin::new_unchecked(&mut this.1),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "background-tasks")]
+impl<A: Tasks, B: Tasks> Tasks for Pair<A, B> {
+    fn poll_tasks(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let (a, b) = self.project();
+        let a = a.poll_tasks(cx);
+        let b = b.poll_tasks(cx);
+        if a.is_ready() || b.is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "background-tasks")]
+impl<SD, PC: ?Sized, A: CollectionTasks<SD, PC>, B: CollectionTasks<SD, PC>> CollectionTasks<SD, PC>
+    for Pair<A, B>
+{
+    fn start(self: Pin<&mut Self>, collection: &mut PC, sd: &SD) {
+        let (a, b) = self.project();
+        a.start(collection, sd);
+        b.start(collection, sd);
+    }
+
+    fn poll_waiting(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let (a, b) = self.project();
+        let a = a.poll_waiting(cx);
+        let b = b.poll_waiting(cx);
+        if a.is_ready() && b.is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    #[cfg(feature = "async-timeout")]
+    fn next_deadline(self: Pin<&mut Self>) -> Option<Duration> {
+        let (a, b) = self.project();
+        match (a.next_deadline(), b.next_deadline()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    #[cfg(feature = "async-timeout")]
+    fn expire(self: Pin<&mut Self>, elapsed: Duration, report: &dyn Fn(&TimeoutReport)) {
+        let (a, b) = self.project();
+        a.expire(elapsed, report);
+        b.expire(elapsed, report);
+    }
+
+    fn apply(self: Pin<&mut Self>, collection: &mut PC, sd: &mut SD) {
+        let (a, b) = self.project();
+        a.apply(collection, sd);
+        b.apply(collection, sd);
+    }
+}
+
+/// The tasks of a collection without plugins, or without the
+/// `background-tasks` feature.
+impl Tasks for () {
+    #[inline(always)]
+    fn poll_tasks(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        Poll::Pending
+    }
+}
+
+impl<SD, PC: ?Sized> CollectionTasks<SD, PC> for () {
+    #[inline(always)]
+    fn start(self: Pin<&mut Self>, _collection: &mut PC, _sd: &SD) {}
+
+    #[inline(always)]
+    fn poll_waiting(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    #[cfg(feature = "async-timeout")]
+    #[inline(always)]
+    fn next_deadline(self: Pin<&mut Self>) -> Option<Duration> {
+        None
+    }
+
+    #[cfg(feature = "async-timeout")]
+    #[inline(always)]
+    fn expire(self: Pin<&mut Self>, _elapsed: Duration, _report: &dyn Fn(&TimeoutReport)) {}
+
+    #[inline(always)]
+    fn apply(self: Pin<&mut Self>, _collection: &mut PC, _sd: &mut SD) {}
+}
+
+const EMPTY: u8 = 0;
+const BUSY: u8 = 1;
+const FULL: u8 = 2;
+
+/// Single-slot, lock-free and allocation-free channel, typically placed
+/// in a `static`, e.g. for an interrupt handler to hand a value to a
+/// plugin.
+///
+/// Example:
+/// ```rust
+/// use typed_ecs::background::Mailbox;
+///
+/// static READINGS: Mailbox<u32> = Mailbox::new();
+///
+/// assert!(READINGS.try_send(1).is_ok());
+/// // Full: the value is handed back.
+/// assert_eq!(READINGS.try_send(2), Err(2));
+/// assert_eq!(READINGS.try_recv(), Some(1));
+/// assert_eq!(READINGS.try_recv(), None);
+/// ```
+pub struct Mailbox<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+    // Sender waiting for the slot to be emptied.
+    waker: AtomicWaker,
+}
+
+// SAFETY: the slot is only accessed by whoever moved `state` to BUSY, and
+// values are moved across, never shared.
+unsafe impl<T: Send> Sync for Mailbox<T> {}
+
+impl<T> Default for Mailbox<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Mailbox<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    /// Stores `value`, or hands it back if the mailbox is already full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(value);
+        }
+        // SAFETY: we own the slot until `state` is released.
+        unsafe { (*self.value.get()).write(value) };
+        self.state.store(FULL, Ordering::Release);
+        Ok(())
+    }
+
+    /// Waits until the mailbox is empty, then stores `value`.
+    pub async fn send(&self, value: T) {
+        let mut value = Some(value);
+        poll_fn(|cx| {
+            let Some(v) = value.take() else {
+                return Poll::Ready(());
+            };
+            match self.try_send(v) {
+                Ok(()) => Poll::Ready(()),
+                Err(v) => {
+                    self.waker.register(cx.waker());
+                    // Emptied in-between: retry before sleeping.
+                    match self.try_send(v) {
+                        Ok(()) => Poll::Ready(()),
+                        Err(v) => {
+                            value = Some(v);
+                            Poll::Pending
+                        }
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// Whether a value is waiting to be received.
+    pub fn is_full(&self) -> bool {
+        self.state.load(Ordering::Acquire) == FULL
+    }
+
+    /// Takes the stored value, if any, and wakes a waiting sender.
+    pub fn try_recv(&self) -> Option<T> {
+        if self
+            .state
+            .compare_exchange(FULL, BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        // SAFETY: FULL guarantees an initialized value, and we own the
+        // slot until `state` is released.
+        let value = unsafe { (*self.value.get()).assume_init_read() };
+        self.state.store(EMPTY, Ordering::Release);
+        self.waker.wake();
+        Some(value)
+    }
+}
+
+impl<T> Drop for Mailbox<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == FULL {
+            // SAFETY: FULL guarantees an initialized value.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
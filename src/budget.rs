@@ -0,0 +1,235 @@
+use core::{cell::UnsafeCell, time::Duration};
+
+use portable_atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Clock and policy the collection checks the time budgets with (see
+/// `Plugin::budget` for the budgets of the systems, and
+/// `Watchdog::schedule_budget` for the ones of the schedules). It is
+/// injected into a collection with `GeneratedPluginCollection::with_watchdog`.
+///
+/// A watchdog is shared by the systems of a parallel schedule, hence the
+/// `Sync` bound.
+pub trait Watchdog: Sync {
+    type Instant: Copy;
+
+    fn now(&self) -> Self::Instant;
+    fn elapsed(&self, since: Self::Instant) -> Duration;
+
+    /// Budget of a whole schedule, e.g. `"Update"`.
+    #[inline(always)]
+    fn schedule_budget(&self, _schedule: &'static str) -> Option<Duration> {
+        None
+    }
+
+    /// Called every time a budget is exceeded, from the thread that ran
+    /// the system (or schedule), right after it returned.
+    #[inline(always)]
+    fn on_violation(&self, _violation: &BudgetViolation) -> OnBudgetExceeded {
+        OnBudgetExceeded::Continue
+    }
+}
+
+/// Default watchdog of a collection: never measures anything, so budgets
+/// are ignored until a real watchdog is injected.
+#[derive(Default, Clone, Copy)]
+pub struct NoWatchdog;
+
+impl Watchdog for NoWatchdog {
+    type Instant = ();
+
+    #[inline(always)]
+    fn now(&self) {}
+
+    #[inline(always)]
+    fn elapsed(&self, _since: ()) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Watchdog measuring with `std::time::Instant`, whose violations are
+/// only counted (see `BudgetState::violations`).
+#[cfg(feature = "std")]
+#[derive(Default, Clone, Copy)]
+pub struct StdWatchdog;
+
+#[cfg(feature = "std")]
+impl Watchdog for StdWatchdog {
+    type Instant = std::time::Instant;
+
+    #[inline(always)]
+    fn now(&self) -> Self::Instant {
+        std::time::Instant::now()
+    }
+
+    #[inline(always)]
+    fn elapsed(&self, since: Self::Instant) -> Duration {
+        since.elapsed()
+    }
+}
+
+/// What the collection does once a budget has been exceeded, as decided by
+/// `Watchdog::on_violation`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnBudgetExceeded {
+    /// The violation is counted, and the app goes on.
+    Continue,
+    /// Requests exit at the next ExitCheck, with
+    /// `ExitReason::BudgetExceeded`.
+    Exit,
+}
+
+/// Which system (or schedule) exceeded its budget.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BudgetViolation {
+    pub schedule: &'static str,
+    /// `None` for the budget of a whole schedule.
+    pub plugin: Option<&'static str>,
+    pub system: Option<&'static str>,
+    pub budget: Duration,
+    pub elapsed: Duration,
+}
+
+/// Budget violations of a collection of `N` plugins, maintained by the
+/// generated code.
+pub struct BudgetState<const N: usize> {
+    plugins: [BudgetSlot; N],
+    schedules: BudgetSlot,
+}
+
+impl<const N: usize> Default for BudgetState<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> BudgetState<N> {
+    pub const fn new() -> Self {
+        Self {
+            plugins: [const { BudgetSlot::new() }; N],
+            schedules: BudgetSlot::new(),
+        }
+    }
+
+    /// Where the violations of the systems of the `idx`th plugin go.
+    #[inline(always)]
+    pub fn plugin(&self, idx: usize) -> &BudgetSlot {
+        &self.plugins[idx]
+    }
+
+    /// Where the violations of the schedules go.
+    #[inline(always)]
+    pub fn schedules(&self) -> &BudgetSlot {
+        &self.schedules
+    }
+
+    /// Number of budgets exceeded so far.
+    pub fn violations(&self) -> u32 {
+        self.slots()
+            .map(|slot| slot.violations.load(Ordering::Relaxed))
+            .fold(0, u32::wrapping_add)
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.slots()
+            .any(|slot| slot.exit_requested.load(Ordering::Acquire))
+    }
+
+    /// Takes a violation that requested exit, if any: the one of the
+    /// schedules, or else of the first plugin.
+    pub fn take_violation(&mut self) -> Option<BudgetViolation> {
+        core::iter::once(&mut self.schedules)
+            .chain(&mut self.plugins)
+            .find_map(|slot| slot.exit.get_mut().take())
+    }
+
+    fn slots(&self) -> impl Iterator<Item = &BudgetSlot> {
+        core::iter::once(&self.schedules).chain(&self.plugins)
+    }
+}
+
+/// Violations of a plugin's systems, or of the schedules. Only the thread
+/// running them writes to it, so plain loads and stores are enough.
+pub struct BudgetSlot {
+    violations: AtomicU32,
+    exit_requested: AtomicBool,
+    // First violation that requested exit, written before
+    // `exit_requested` is set.
+    exit: UnsafeCell<Option<BudgetViolation>>,
+}
+
+// SAFETY: `exit` is written by the single writer of the slot until
+// `exit_requested` is set, and only read through `&mut BudgetState`.
+unsafe impl Sync for BudgetSlot {}
+
+impl BudgetSlot {
+    const fn new() -> Self {
+        Self {
+            violations: AtomicU32::new(0),
+            exit_requested: AtomicBool::new(false),
+            exit: UnsafeCell::new(None),
+        }
+    }
+
+    fn violated<W: Watchdog>(&self, watchdog: &W, violation: BudgetViolation) {
+        let violations = self.violations.load(Ordering::Relaxed);
+        self.violations
+            .store(violations.wrapping_add(1), Ordering::Relaxed);
+        if watchdog.on_violation(&violation) == OnBudgetExceeded::Exit
+            && !self.exit_requested.load(Ordering::Relaxed)
+        {
+            // SAFETY: see `impl Sync`.
+            unsafe { *self.exit.get() = Some(violation) };
+            self.exit_requested.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Checks a budget when dropped, that is once the system (or schedule)
+/// it has been created for returns. Created by the generated collection.
+pub struct BudgetGuard<'a, W: Watchdog> {
+    watchdog: &'a W,
+    slot: &'a BudgetSlot,
+    // Nothing is measured without a budget.
+    budget: Option<(Duration, W::Instant)>,
+    labels: (&'static str, Option<&'static str>, Option<&'static str>),
+}
+
+impl<'a, W: Watchdog> BudgetGuard<'a, W> {
+    #[inline(always)]
+    pub fn start(
+        watchdog: &'a W,
+        slot: &'a BudgetSlot,
+        budget: Option<Duration>,
+        labels: (&'static str, Option<&'static str>, Option<&'static str>),
+    ) -> Self {
+        Self {
+            watchdog,
+            slot,
+            budget: budget.map(|budget| (budget, watchdog.now())),
+            labels,
+        }
+    }
+}
+
+impl<W: Watchdog> Drop for BudgetGuard<'_, W> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let Some((budget, start)) = self.budget else {
+            return;
+        };
+        let elapsed = self.watchdog.elapsed(start);
+        if elapsed > budget {
+            let (schedule, plugin, system) = self.labels;
+            self.slot.violated(
+                self.watchdog,
+                BudgetViolation {
+                    schedule,
+                    plugin,
+                    system,
+                    budget,
+                    elapsed,
+                },
+            );
+        }
+    }
+}
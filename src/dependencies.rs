@@ -0,0 +1,255 @@
+//! Plugins requiring, or excluding, other plugins in their collection.
+//!
+//! A plugin declares the plugins it can't work without by implementing
+//! [`Requires`], as a tuple of plugin types. `generate_collection!` checks
+//! them at compile time, naming the missing dependency:
+//!
+//! ```compile_fail,E0277
+//! use typed_ecs::{
+//!     dependencies::Requires,
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     shared_data::SharedData,
+//! };
+//!
+//! struct TimePlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for TimePlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! struct PhysicsPlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for PhysicsPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! impl Requires for PhysicsPlugin {
+//!     type Plugins = (TimePlugin,);
+//! }
+//!
+//! // error[E0277]: `PhysicsPlugin` requires the `TimePlugin` plugin,
+//! // missing from the collection
+//! generate_collection!(PhysicsPlugin);
+//! ```
+//!
+//! Plugins not implementing `Requires` have no dependencies. The check
+//! only looks at the types of the collection: the order of the plugins is
+//! up to the user.
+//!
+//! A tuple holds up to 12 plugins, as do the tuples of the standard
+//! library: past that, nest them, e.g. `((A, B, C), D)`.
+//!
+//! Plugins that must never coexist, such as two logger backends, are
+//! declared by implementing [`Conflicts`] on either of them, once per
+//! conflicting plugin:
+//!
+//! ```compile_fail,E0277
+//! use typed_ecs::{
+//!     dependencies::Conflicts,
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     shared_data::SharedData,
+//! };
+//!
+//! struct UartLogPlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for UartLogPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! struct RttLogPlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for RttLogPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! impl Conflicts<UartLogPlugin> for RttLogPlugin {}
+//!
+//! // error[E0277]: `RttLogPlugin` and `UartLogPlugin` conflict: they can't
+//! // be in the same collection
+//! generate_collection!(UartLogPlugin, RttLogPlugin);
+//! ```
+
+use core::marker::PhantomData;
+
+/// Plugins required in the collection of `Self`.
+pub trait Requires {
+    /// A plugin type, or a tuple of them, e.g. `(TimePlugin, InputPlugin)`.
+    type Plugins;
+}
+
+/// A plugin that can't be in the collection of `Self`. Declaring the
+/// conflict on one of the two plugins is enough.
+pub trait Conflicts<P> {}
+
+/// Implemented by `generate_collection!`, on a marker type, for each of
+/// the plugins of the collection: `By` is the plugin requiring `P`. Also
+/// implemented for the tuples of plugins it contains.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{By}` requires the `{P}` plugin, missing from the collection",
+    label = "add `{P}` to this collection"
+)]
+pub trait Contains<P, By> {}
+
+impl<C, By> Contains<(), By> for C {}
+
+macro_rules! contains_tuple {
+    ($($ty:ident),+) => {
+        impl<C, By, $($ty),+> Contains<($($ty,)+), By> for C where $(C: Contains<$ty, By>),+ {}
+    };
+}
+
+contains_tuple!(P0);
+contains_tuple!(P0, P1);
+contains_tuple!(P0, P1, P2);
+contains_tuple!(P0, P1, P2, P3);
+contains_tuple!(P0, P1, P2, P3, P4);
+contains_tuple!(P0, P1, P2, P3, P4, P5);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6, P7);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10);
+contains_tuple!(P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11);
+
+// Whether a plugin implements `Requires` can't be told by a trait bound:
+// the generated collection calls `(&&Probe::<P>::new()).dependencies()`,
+// which resolves to `ViaRequires` when it does (one auto-deref), and to
+// `ViaNothing` otherwise (two auto-derefs).
+
+#[doc(hidden)]
+pub struct Probe<P>(PhantomData<P>);
+
+impl<P> Probe<P> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P> Default for Probe<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub struct Declared<P>(PhantomData<P>);
+
+impl<P: Requires> Declared<P> {
+    /// Fails to compile when `C` misses one of the requirements of `P`.
+    pub fn check<C>(self)
+    where
+        C: Contains<P::Plugins, P>,
+    {
+    }
+}
+
+#[doc(hidden)]
+pub struct Undeclared;
+
+impl Undeclared {
+    pub fn check<C>(self) {}
+}
+
+#[doc(hidden)]
+pub trait ViaRequires<P> {
+    fn dependencies(&self) -> Declared<P>;
+}
+
+impl<P: Requires> ViaRequires<P> for &Probe<P> {
+    fn dependencies(&self) -> Declared<P> {
+        Declared(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaNothing {
+    fn dependencies(&self) -> Undeclared;
+}
+
+impl<P> ViaNothing for Probe<P> {
+    fn dependencies(&self) -> Undeclared {
+        Undeclared
+    }
+}
+
+/// Implemented by no type: the bound of [`Conflict::check`].
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` and `{P}` conflict: they can't be in the same collection",
+    label = "remove `{Self}` or `{P}` from this collection",
+    note = "`{Self}` implements `Conflicts<{P}>`"
+)]
+pub trait Compatible<P> {}
+
+// Each plugin of the collection is paired with every other one, in both
+// orders: `(&&Pair::<P, X>::new()).conflict()` resolves to
+// `ViaConflicts` when `P` implements `Conflicts<X>`, and to
+// `ViaNoConflicts` otherwise.
+
+#[doc(hidden)]
+pub struct Pair<P, X>(PhantomData<(P, X)>);
+
+impl<P, X> Pair<P, X> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P, X> Default for Pair<P, X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `P` declares a conflict with `X`, a plugin of the collection.
+#[doc(hidden)]
+pub struct Conflict<P, X>(PhantomData<(P, X)>);
+
+impl<P, X> Conflict<P, X> {
+    /// Fails to compile.
+    pub fn check(self)
+    where
+        P: Compatible<X>,
+    {
+    }
+}
+
+#[doc(hidden)]
+pub struct NoConflict;
+
+impl NoConflict {
+    pub fn check(self) {}
+}
+
+#[doc(hidden)]
+pub trait ViaConflicts<P, X> {
+    fn conflict(&self) -> Conflict<P, X>;
+}
+
+impl<P: Conflicts<X>, X> ViaConflicts<P, X> for &Pair<P, X> {
+    fn conflict(&self) -> Conflict<P, X> {
+        Conflict(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaNoConflicts {
+    fn conflict(&self) -> NoConflict;
+}
+
+impl<P, X> ViaNoConflicts for Pair<P, X> {
+    fn conflict(&self) -> NoConflict {
+        NoConflict
+    }
+}
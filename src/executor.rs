@@ -1,90 +1,92 @@
 #![allow(async_fn_in_trait)]
 
-#[cfg(feature = "profile")]
-use tracing::trace;
+use core::pin::pin;
 
-use crate::{app::App, plugin_collection::PluginCollection, shared_data::SharedData, should_exit::ShouldExit};
+use crate::{
+    app::App, instrumentation::Instrumentation, plugin_collection::PluginCollection,
+    shared_data::SharedData, should_exit::ExitReason,
+};
 
+/// Runs the schedules in the default order (see [`ExecutorTrait::run`]),
+/// looping as fast as possible.
 pub struct DefaultExecutor;
 
-impl ExecutorTrait for DefaultExecutor {
-    fn init() -> Self {
-        Self
-    }
-    async fn run<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
-        &mut self,
-        app: &mut App<SD, PC, Executor>,
-    ) {
-        let mut should_exit = false;
-
-        #[cfg(all(feature = "parallel-global-pool", debug_assertions))]
-        if rayon::ThreadPoolBuilder::new()
-            // .num_threads(PC::PLUGIN_NUM)
-            .thread_name(|i| std::format!("ecs-worker-{i}"))
-            .build_global()
-            .is_err()
-        {
-            std::eprintln!("Unable to create a global thread pool. One already exists ?");
-        }
-
-        // If not in build with dbg assertions, silently fail
-        #[cfg(all(feature = "parallel-global-pool", not(debug_assertions)))]
-        let _ = rayon::ThreadPoolBuilder::new()
-            // .num_threads(PC::PLUGIN_NUM)
-            .thread_name(|i| std::format!("ecs-worker-{i}"))
-            .build_global();
+impl ExecutorTrait for DefaultExecutor {}
 
-        app.plugin_collection.startup_all(&app.shared_data);
-        app.plugin_collection
-            .apply_startup_all(&mut app.shared_data);
+/// Drives an app, passed to `App::new_with_executor`.
+pub trait ExecutorTrait {
+    /// Drives the app: startup, then frames until a plugin requests exit.
+    ///
+    /// The default implementation is built on `App::run_startup` and
+    /// `App::run_frame`: custom executors usually only override the
+    /// `before_frame`/`after_frame` hooks, and reuse these building
+    /// blocks when they really have to change the loop itself.
+    async fn run<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+    ) -> ExitReason {
+        app.run_startup().await;
 
-        app.plugin_collection
-            .async_startup_all(&app.shared_data)
-            .await;
-        app.plugin_collection
-            .apply_async_startup_all(&mut app.shared_data);
+        let mut tasks = pin!(app.background_tasks());
 
         loop {
-            app.plugin_collection.pre_update_all(&app.shared_data);
-            app.plugin_collection
-                .apply_pre_update_all(&mut app.shared_data);
-
-            app.plugin_collection.update_all(&app.shared_data);
-            app.plugin_collection.apply_update_all(&mut app.shared_data);
-
-            app.plugin_collection.post_update_all(&app.shared_data);
-            app.plugin_collection
-                .apply_post_update_all(&mut app.shared_data);
-
-            app.plugin_collection
-                .exit_check_all(&mut should_exit, &app.shared_data);
+            self.before_frame(app);
+            let should_exit = app.run_frame(tasks.as_mut()).await;
+            self.after_frame(app, should_exit);
 
-            if should_exit.is_true() {
-                break;
+            if should_exit {
+                return app.exit_reason();
             }
-
-            app.plugin_collection
-                .async_update_all(&app.shared_data)
-                .await;
-            app.plugin_collection
-                .apply_async_update_all(&mut app.shared_data);
         }
     }
 
-    fn run_exit_hooks<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
-        app: &mut App<SD, PC, Executor>,
+    /// Hook, called at the beginning of every frame, before PreUpdate.
+    #[inline(always)]
+    fn before_frame<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        _app: &mut App<SD, PC, Executor, I>,
     ) {
-        app.plugin_collection.on_exit_all(&app.shared_data);
     }
-}
 
-pub trait ExecutorTrait {
-    fn init() -> Self;
-    async fn run<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
+    /// Hook, called at the end of every frame, including the last one,
+    /// in which case `should_exit` is true (and AsyncUpdate has been
+    /// skipped).
+    #[inline(always)]
+    fn after_frame<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        _app: &mut App<SD, PC, Executor, I>,
+        _should_exit: bool,
+    ) {
+    }
+
+    /// Called exactly once by `App::run`, when the run is over (or has
+    /// been interrupted, see `ExitReason`). Runs the OnExit schedule by
+    /// default: override it to skip, defer or wrap the exit hooks.
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
         &mut self,
-        app: &mut App<SD, PC, Executor>,
-    );
-    fn run_exit_hooks<SD: SharedData, PC: PluginCollection<SD>, Executor: ExecutorTrait>(
-        app: &mut App<SD, PC, Executor>,
-    );
+        app: &mut App<SD, PC, Executor, I>,
+        _reason: &ExitReason,
+    ) {
+        app.run_shutdown();
+    }
 }
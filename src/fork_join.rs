@@ -0,0 +1,74 @@
+/// Fork-join primitive the generated collection uses to run the
+/// non-applying systems of a schedule in parallel (`fork-join` feature).
+///
+/// The collection splits its plugins in two halves, recursively, and
+/// joins them with this method: a backend only has to know how to run
+/// two closures at once, and wait for both. Since nothing is boxed nor
+/// queued, a backend can be written for `no_std` and `no_alloc` targets,
+/// e.g. by running `b` on the second core of a dual core MCU.
+///
+/// Both closures must have returned (or panicked) before `join` returns,
+/// as they borrow the plugins and the SharedData.
+///
+/// Available backends:
+/// - [`Sequential`]: runs `a` then `b` on the calling thread.
+/// - [`StdThreads`]: spawns `b` on a scoped `std` thread (`std` feature).
+/// - [`EcsThreadPool`](crate::thread_pool::EcsThreadPool): rayon's
+///   work-stealing pool (`parallel` feature).
+pub trait ForkJoin: Sync {
+    fn join<A, B>(&self, a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send;
+}
+
+/// Backend of the collections built with `build_generated_collection`.
+#[cfg(feature = "parallel")]
+pub type DefaultForkJoin = crate::thread_pool::EcsThreadPool;
+/// Backend of the collections built with `build_generated_collection`.
+#[cfg(not(feature = "parallel"))]
+pub type DefaultForkJoin = Sequential;
+
+/// No parallelism at all: the systems run one after the other, just
+/// like without the `fork-join` feature.
+#[derive(Default, Clone, Copy)]
+pub struct Sequential;
+
+impl ForkJoin for Sequential {
+    #[inline(always)]
+    fn join<A, B>(&self, a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send,
+    {
+        a();
+        b();
+    }
+}
+
+/// Reference backend built on `std::thread::scope`: `b` runs on a freshly
+/// spawned thread while `a` runs on the calling one.
+///
+/// A thread is spawned for each plugin but one, on every schedule, so this
+/// is mostly useful to test plugins and custom backends on a desktop.
+/// Prefer the `parallel` feature for real workloads.
+#[cfg(feature = "std")]
+#[derive(Default, Clone, Copy)]
+pub struct StdThreads;
+
+#[cfg(feature = "std")]
+impl ForkJoin for StdThreads {
+    fn join<A, B>(&self, a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send,
+    {
+        std::thread::scope(|s| {
+            let handle = s.spawn(b);
+            a();
+            if let Err(payload) = handle.join() {
+                std::panic::resume_unwind(payload);
+            }
+        });
+    }
+}
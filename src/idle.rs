@@ -0,0 +1,128 @@
+//! Sleeping between frames while no plugin has work.
+//!
+//! `DefaultExecutor` runs frames back to back. With [`IdleExecutor`], every
+//! plugin reports at the end of a frame, in `Plugin::idle`, whether it has
+//! work for the next one: if none has, the executor awaits a [`Wait`]
+//! future until an event (an interrupt, a signal) or the nearest deadline
+//! of the plugins, instead of looping.
+//!
+//! The `Wait` implementation is the platform's: `cortex_m::asm::wfi()`, an
+//! embassy `Signal` raced with a `Timer`, or a `std` condition variable
+//! (see `examples/idle.rs`). Background and async tasks are still polled
+//! while the executor waits, and a result of theirs ends the wait.
+
+#![allow(async_fn_in_trait)]
+
+use core::{
+    future::{Future, poll_fn},
+    pin::pin,
+    task::Poll,
+    time::Duration,
+};
+
+use crate::{
+    app::App, background::Tasks, executor::ExecutorTrait, instrumentation::Instrumentation,
+    plugin_collection::PluginCollection, shared_data::SharedData, should_exit::ExitReason,
+};
+
+/// The report of a plugin (or of a collection) at the end of a frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Idle {
+    /// Has work for the next frame.
+    Busy,
+    /// Has no work until an event, e.g. an interrupt handler pushing
+    /// into its queue.
+    UntilEvent,
+    /// Has no work for this long, at most (e.g. until the next blink),
+    /// nor until an event.
+    For(Duration),
+}
+
+impl Idle {
+    /// Combines the reports of two plugins: the collection is only idle
+    /// if both are, until the nearest of their deadlines.
+    ///
+    /// ```rust
+    /// use core::time::Duration;
+    /// use typed_ecs::idle::Idle;
+    ///
+    /// let blink = Idle::For(Duration::from_millis(100));
+    /// assert_eq!(Idle::UntilEvent.and(blink), blink);
+    /// assert_eq!(blink.and(Idle::For(Duration::from_millis(20))), Idle::For(Duration::from_millis(20)));
+    /// assert_eq!(blink.and(Idle::Busy), Idle::Busy);
+    /// ```
+    pub const fn and(self, other: Idle) -> Idle {
+        match (self, other) {
+            (Idle::Busy, _) | (_, Idle::Busy) => Idle::Busy,
+            (Idle::UntilEvent, idle) | (idle, Idle::UntilEvent) => idle,
+            (Idle::For(a), Idle::For(b)) => {
+                if a.as_nanos() <= b.as_nanos() {
+                    Idle::For(a)
+                } else {
+                    Idle::For(b)
+                }
+            }
+        }
+    }
+}
+
+/// How the [`IdleExecutor`] sleeps.
+pub trait Wait {
+    /// Returns once an event may have happened, or `timeout` (when some)
+    /// has elapsed. Returning early is fine: the executor runs a frame,
+    /// then asks the plugins again.
+    async fn wait(&mut self, timeout: Option<Duration>);
+}
+
+/// Runs the schedules in the default order, awaiting `W` between two
+/// frames while every plugin is idle.
+pub struct IdleExecutor<W> {
+    wait: W,
+}
+
+impl<W: Wait> IdleExecutor<W> {
+    /// Passed to `App::new_with_executor`.
+    pub const fn new(wait: W) -> Self {
+        Self { wait }
+    }
+}
+
+impl<W: Wait> ExecutorTrait for IdleExecutor<W> {
+    async fn run<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+    ) -> ExitReason {
+        app.run_startup().await;
+
+        let mut tasks = pin!(app.background_tasks());
+
+        loop {
+            self.before_frame(app);
+            let should_exit = app.run_frame(tasks.as_mut()).await;
+            self.after_frame(app, should_exit);
+
+            if should_exit {
+                return app.exit_reason();
+            }
+
+            let timeout = match app.idle() {
+                Idle::Busy => continue,
+                Idle::UntilEvent => None,
+                Idle::For(timeout) => Some(timeout),
+            };
+            let mut wait = pin!(self.wait.wait(timeout));
+            poll_fn(|cx| {
+                if tasks.as_mut().poll_tasks(cx).is_ready() {
+                    return Poll::Ready(());
+                }
+                wait.as_mut().poll(cx)
+            })
+            .await;
+        }
+    }
+}
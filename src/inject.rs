@@ -0,0 +1,266 @@
+//! Data from interrupt handlers into `SharedData`.
+//!
+//! An interrupt handler can't borrow the shared data: it pushes what it
+//! read into an [`IsrQueue`] (or a [`Mailbox`](crate::background::Mailbox))
+//! in a `static`, without locks nor allocation. The [`InjectPlugin`] of the
+//! collection drains it in the `ApplyPreUpdate` schedule, calling
+//! [`Inject::inject`] for every item, so the `update` systems of a frame see
+//! everything received before it.
+//!
+//! ```rust
+//! use core::pin::pin;
+//!
+//! use typed_ecs::{
+//!     app::App,
+//!     inject::{Inject, InjectPlugin, IsrQueue},
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     shared_data::SharedData,
+//! };
+//!
+//! #[derive(Default)]
+//! struct Sensors {
+//!     temperature: i16,
+//!     samples: u32,
+//! }
+//!
+//! impl SharedData for Sensors {
+//!     fn build() -> Self {
+//!         Self::default()
+//!     }
+//! }
+//!
+//! // Pushed by the ADC interrupt handler.
+//! static TEMPERATURES: IsrQueue<i16, 8> = IsrQueue::new();
+//!
+//! struct Temperatures;
+//!
+//! impl Inject<Sensors> for Temperatures {
+//!     type Source = IsrQueue<i16, 8>;
+//!
+//!     fn source() -> &'static Self::Source {
+//!         &TEMPERATURES
+//!     }
+//!
+//!     fn inject(sd: &mut Sensors, temperature: i16) {
+//!         sd.temperature = temperature;
+//!         sd.samples += 1;
+//!     }
+//! }
+//!
+//! type TemperaturePlugin = InjectPlugin<Temperatures>;
+//!
+//! generate_collection!(TemperaturePlugin);
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut app = App::new(build_generated_collection::<Sensors>());
+//! app.run_startup().await;
+//!
+//! // A thread standing for the interrupt handler.
+//! std::thread::spawn(|| {
+//!     for temperature in [19, 20, 21] {
+//!         TEMPERATURES.push(temperature).unwrap();
+//!     }
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! let mut tasks = pin!(app.background_tasks());
+//! app.run_frame(tasks.as_mut()).await;
+//! assert_eq!(app.shared_data.temperature, 21);
+//! assert_eq!(app.shared_data.samples, 3);
+//! # }
+//! ```
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use crate::{
+    background::Mailbox, idle::Idle, introspection::Systems, plugin::Plugin,
+    shared_data::SharedData,
+};
+
+/// Lock-free, allocation-free, bounded FIFO queue of `N` items, with one
+/// producer (e.g. an interrupt handler) and one consumer (e.g. an
+/// [`InjectPlugin`]).
+///
+/// Only atomic loads and stores are used, so it works on cores without
+/// compare-and-swap (e.g. `thumbv6m`). In exchange, `push` must only be
+/// called from one context at a time, and so must `pop`: two interrupt
+/// handlers pushing into the same queue must not preempt each other.
+pub struct IsrQueue<T, const N: usize> {
+    slots: [UnsafeCell<MaybeUninit<T>>; N],
+    // Index of the next item to pop, and of the next one to push, both
+    // wrapping at `2 * N`: `tail - head` (modulo `2 * N`) items are in the
+    // queue, from 0 when empty to `N` when full. Wrapping at a multiple of
+    // `N` keeps the slot of an index (`index % N`) the same across laps.
+    // Written by the consumer only.
+    head: AtomicUsize,
+    // Written by the producer only, as is `dropped`.
+    tail: AtomicUsize,
+    dropped: AtomicU32,
+}
+
+// SAFETY: a slot is written by the producer only between `head` and `tail`
+// being checked and `tail` being released, and read by the consumer only
+// after, there being a single producer and a single consumer.
+unsafe impl<T: Send, const N: usize> Sync for IsrQueue<T, N> {}
+
+impl<T, const N: usize> Default for IsrQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> IsrQueue<T, N> {
+    pub const fn new() -> Self {
+        assert!(N > 0, "an IsrQueue holds at least one item");
+        Self {
+            slots: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Appends `value`, or hands it back if the queue is full (the item is
+    /// then counted as dropped). Producer side.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if Self::count(head, tail) == N {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped
+                .store(dropped.wrapping_add(1), Ordering::Relaxed);
+            return Err(value);
+        }
+        // SAFETY: the slot is free (popped, or never written), and only
+        // the producer writes slots.
+        unsafe { (*self.slots[tail % N].get()).write(value) };
+        self.tail.store(Self::next(tail), Ordering::Release);
+        Ok(())
+    }
+
+    /// Takes the oldest item, if any. Consumer side.
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+        // SAFETY: the slot has been written by a push, released with
+        // `tail`, and only the consumer reads slots.
+        let value = unsafe { (*self.slots[head % N].get()).assume_init_read() };
+        self.head.store(Self::next(head), Ordering::Release);
+        Some(value)
+    }
+
+    /// Number of items in the queue.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        Self::count(head, self.tail.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of items rejected by `push` so far.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn next(index: usize) -> usize {
+        if index + 1 == 2 * N { 0 } else { index + 1 }
+    }
+
+    fn count(head: usize, tail: usize) -> usize {
+        (tail + 2 * N - head) % (2 * N)
+    }
+}
+
+impl<T, const N: usize> Drop for IsrQueue<T, N> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// The consumer end of a queue an [`InjectPlugin`] drains.
+pub trait Receiver: Sync {
+    type Item;
+
+    fn try_recv(&self) -> Option<Self::Item>;
+
+    fn is_empty(&self) -> bool;
+}
+
+impl<T: Send, const N: usize> Receiver for IsrQueue<T, N> {
+    type Item = T;
+
+    fn try_recv(&self) -> Option<T> {
+        self.pop()
+    }
+
+    fn is_empty(&self) -> bool {
+        IsrQueue::is_empty(self)
+    }
+}
+
+impl<T: Send> Receiver for Mailbox<T> {
+    type Item = T;
+
+    fn try_recv(&self) -> Option<T> {
+        Mailbox::try_recv(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        !Mailbox::is_full(self)
+    }
+}
+
+/// Where the items of an [`InjectPlugin`] come from, and what they change
+/// in the shared data.
+pub trait Inject<SD: SharedData> {
+    type Source: Receiver + 'static;
+
+    /// The queue, usually a `static`.
+    fn source() -> &'static Self::Source;
+
+    /// Applies one item, in the order they have been pushed.
+    fn inject(sd: &mut SD, item: <Self::Source as Receiver>::Item);
+}
+
+/// Drains the source of `I` into the shared data, in `apply_pre_update`.
+/// Idle (see `idle::IdleExecutor`) while the source is empty.
+///
+/// Being generic, it is added to a collection through a type alias:
+/// `type SensorPlugin = InjectPlugin<Sensor>;`, then
+/// `generate_collection!(SensorPlugin, ...)`.
+pub struct InjectPlugin<I>(PhantomData<fn() -> I>);
+
+impl<SD: SharedData, I: Inject<SD>> Plugin<SD> for InjectPlugin<I> {
+    const SYSTEMS: Systems = Systems::NONE.with("apply_pre_update");
+
+    fn build() -> Self {
+        Self(PhantomData)
+    }
+
+    fn apply_pre_update(&mut self, sd: &mut SD) {
+        while let Some(item) = I::source().try_recv() {
+            I::inject(sd, item);
+        }
+    }
+
+    fn idle(&self, _sd: &SD) -> Idle {
+        if I::source().is_empty() {
+            Idle::UntilEvent
+        } else {
+            Idle::Busy
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::guard::NoopGuard;
+
+/// Hooks the collection calls around every schedule and every plugin
+/// system, e.g. to open profiling spans, count calls or log. The guards
+/// they return are dropped once the schedule (or system) is over.
+///
+/// An app is generic over its instrumentation (see
+/// `App::with_instrumentation`), which defaults to
+/// [`DefaultInstrumentation`]. With a ZST whose guards are ZSTs, the hooks
+/// get optimized away.
+///
+/// The systems of a parallel schedule call the hooks from their worker
+/// threads, hence the `Sync` bound.
+pub trait Instrumentation: Sync {
+    /// Hook, called individually once per schedule (e.g. on the
+    /// beginning of Startup, Update, ...).
+    fn on_schedule_start(&self, schedule: &'static str) -> impl Drop;
+
+    /// Hook, called individually once per plugin system call.
+    fn on_system_start(
+        &self,
+        schedule: &'static str,
+        plugin: &'static str,
+        system: &'static str,
+    ) -> impl Drop;
+}
+
+/// Instrumentation doing nothing.
+#[derive(Default, Clone, Copy)]
+pub struct NoInstrumentation;
+
+impl Instrumentation for NoInstrumentation {
+    #[inline(always)]
+    fn on_schedule_start(&self, _schedule: &'static str) -> impl Drop {
+        NoopGuard
+    }
+
+    #[inline(always)]
+    fn on_system_start(
+        &self,
+        _schedule: &'static str,
+        _plugin: &'static str,
+        _system: &'static str,
+    ) -> impl Drop {
+        NoopGuard
+    }
+}
+
+/// Instrumentation of an app, unless another one is given: chosen with
+/// the enabled features.
+/// - `profile`: `tracing` spans (`profile::Tracing`)
+/// - `profile-lite`: cycle-count statistics (`profile_lite::ProfileLite`)
+/// - otherwise: [`NoInstrumentation`]
+#[cfg(feature = "profile")]
+pub type DefaultInstrumentation = crate::profile::Tracing;
+#[cfg(feature = "profile-lite")]
+pub type DefaultInstrumentation = crate::profile_lite::ProfileLite;
+#[cfg(not(any(feature = "profile", feature = "profile-lite")))]
+pub type DefaultInstrumentation = NoInstrumentation;
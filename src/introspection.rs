@@ -0,0 +1,491 @@
+//! Which plugins run in which schedule, in what order: the metadata a
+//! collection exposes as `PluginCollection::INFO` (or `App::collection_info`),
+//! rendered as a Graphviz DOT graph or as a text table.
+//!
+//! The systems a plugin implements can't be told from its type: they are
+//! declared by the `Plugin::SYSTEMS` constant, which the
+//! `#[typed_ecs::macros::systems]` attribute fills in from the methods of
+//! the `impl Plugin` block. Without it, the systems of a plugin are
+//! unknown: it is shown apart from the schedules.
+//!
+//! ```rust
+//! use typed_ecs::{
+//!     macros::{generate_collection, systems},
+//!     plugin::Plugin,
+//!     plugin_collection::PluginCollection,
+//!     shared_data::{PhantomSharedData, SharedData},
+//! };
+//!
+//! struct InputPlugin;
+//!
+//! #[systems]
+//! impl<SD: SharedData> Plugin<SD> for InputPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//!
+//!     fn apply_pre_update(&mut self, _sd: &mut SD) {}
+//! }
+//!
+//! struct PhysicsPlugin;
+//!
+//! #[systems]
+//! impl<SD: SharedData> Plugin<SD> for PhysicsPlugin {
+//!     const NAME: &'static str = "physics::PhysicsPlugin";
+//!
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//!
+//!     fn update(&mut self, _sd: &SD) {}
+//!     fn apply_update(&mut self, _sd: &mut SD) {}
+//! }
+//!
+//! generate_collection!(InputPlugin, PhysicsPlugin);
+//! let info = <GeneratedPluginCollection<PhantomSharedData> as PluginCollection<_>>::INFO;
+//!
+//! let table = info.table().to_string();
+//! assert!(table.contains("ApplyPreUpdate"));
+//! assert!(table.contains("InputPlugin"));
+//! assert!(table.contains("physics::PhysicsPlugin"));
+//! // Schedules without any system are left out.
+//! assert!(!table.contains("AsyncUpdate"));
+//!
+//! let dot = info.dot().to_string();
+//! assert!(dot.starts_with("digraph"));
+//! ```
+
+use core::fmt;
+
+/// How the systems of a schedule run relative to each other.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Execution {
+    /// One after another, in the collection order.
+    Sequential,
+    /// Forked on the collection's fork-join backend (`parallel` feature).
+    Parallel,
+    /// Polled concurrently, on the calling thread.
+    Concurrent,
+}
+
+impl Execution {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Execution::Sequential => "sequential",
+            Execution::Parallel => "parallel",
+            Execution::Concurrent => "concurrent",
+        }
+    }
+}
+
+/// A schedule, and the plugin system it runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Schedule {
+    pub name: &'static str,
+    pub system: &'static str,
+}
+
+const fn schedule(name: &'static str, system: &'static str) -> Schedule {
+    Schedule { name, system }
+}
+
+/// Every schedule, in the order of `Plugin` (and of the `system-stats`
+/// table). They run in this order, except for ExitCheck, which runs right
+/// after ApplyAsyncPostUpdate (AsyncUpdate and ApplyAsyncUpdate being
+/// skipped on the last frame).
+pub const SCHEDULES: [Schedule; 18] = [
+    schedule("Startup", "startup"),
+    schedule("ApplyStartup", "apply_startup"),
+    schedule("AsyncStartup", "async_startup"),
+    schedule("ApplyAsyncStartup", "apply_async_startup"),
+    schedule("PreUpdate", "pre_update"),
+    schedule("ApplyPreUpdate", "apply_pre_update"),
+    schedule("AsyncPreUpdate", "async_pre_update"),
+    schedule("ApplyAsyncPreUpdate", "apply_async_pre_update"),
+    schedule("Update", "update"),
+    schedule("ApplyUpdate", "apply_update"),
+    schedule("PostUpdate", "post_update"),
+    schedule("ApplyPostUpdate", "apply_post_update"),
+    schedule("AsyncPostUpdate", "async_post_update"),
+    schedule("ApplyAsyncPostUpdate", "apply_async_post_update"),
+    schedule("AsyncUpdate", "async_update"),
+    schedule("ApplyAsyncUpdate", "apply_async_update"),
+    schedule("ExitCheck", "exit_check"),
+    schedule("OnExit", "on_exit"),
+];
+
+/// Indices of the `SCHEDULES`, in the order they run.
+const RUN_ORDER: [usize; 18] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 16, 14, 15, 17];
+
+const FIRST_LOOP_SCHEDULE: usize = 4;
+const EXIT_CHECK: usize = 16;
+const ON_EXIT: usize = 17;
+
+/// Set of the systems of a plugin, one bit per schedule of [`SCHEDULES`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Systems(u32);
+
+impl Systems {
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << SCHEDULES.len()) - 1);
+    /// Systems not declared: contains none of them, but isn't `NONE`.
+    pub const UNKNOWN: Self = Self(1 << 31);
+
+    /// Bit `i` is the system of `SCHEDULES[i]`.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// The system of a schedule, by its name (e.g. `"apply_update"`).
+    pub const fn with(self, system: &str) -> Self {
+        let mut idx = 0;
+        while idx < SCHEDULES.len() {
+            if str_eq(SCHEDULES[idx].system, system) {
+                return Self(self.0 & Self::ALL.0 | 1 << idx);
+            }
+            idx += 1;
+        }
+        panic!("not a plugin system");
+    }
+
+    /// Whether the system of `SCHEDULES[schedule]` is in the set.
+    pub const fn contains(self, schedule: usize) -> bool {
+        self.0 & 1 << schedule != 0
+    }
+
+    pub const fn is_known(self) -> bool {
+        self.0 != Self::UNKNOWN.0
+    }
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A plugin of a collection, as described by its `Plugin` constants.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PluginInfo {
+    /// `Plugin::NAME`, or the type as written in `generate_collection!`.
+    pub name: &'static str,
+    pub version: &'static str,
+    pub description: &'static str,
+    pub systems: Systems,
+    /// Of the plugin field of the collection.
+    pub footprint: Footprint,
+}
+
+/// Size and alignment of a type, in bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Footprint {
+    pub size: usize,
+    pub align: usize,
+}
+
+impl Footprint {
+    pub const fn of<T>() -> Self {
+        Self {
+            size: size_of::<T>(),
+            align: align_of::<T>(),
+        }
+    }
+}
+
+/// `Plugin::NAME`, unless empty. Called by the generated collection.
+#[doc(hidden)]
+pub const fn plugin_name(name: &'static str, type_name: &'static str) -> &'static str {
+    if name.is_empty() { type_name } else { name }
+}
+
+/// Metadata of a generated collection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CollectionInfo {
+    /// In the collection order, which is the order the systems of a
+    /// sequential schedule run in.
+    pub plugins: &'static [PluginInfo],
+    /// Whether the collection has been generated with the `parallel`
+    /// feature.
+    pub parallel: bool,
+    /// How the systems of each schedule of [`SCHEDULES`] run, as
+    /// generated.
+    pub execution: [Execution; SCHEDULES.len()],
+    /// Of the whole collection: its plugins, and the state the framework
+    /// keeps along (timer, watchdog, statistics...).
+    pub collection: Footprint,
+    pub shared_data: Footprint,
+}
+
+impl CollectionInfo {
+    /// The plugins implementing the system of `SCHEDULES[schedule]`, in
+    /// order.
+    pub fn plugins_in(&self, schedule: usize) -> impl Iterator<Item = &'static PluginInfo> {
+        self.plugins
+            .iter()
+            .filter(move |plugin| plugin.systems.contains(schedule))
+    }
+
+    /// The plugins whose systems are unknown (see `Plugin::SYSTEMS`), in
+    /// order.
+    pub fn unknown_plugins(&self) -> impl Iterator<Item = &'static PluginInfo> {
+        self.plugins
+            .iter()
+            .filter(|plugin| !plugin.systems.is_known())
+    }
+
+    /// Renders the schedules as a Graphviz DOT graph, each system being a
+    /// node: `dot -Tsvg` draws it.
+    pub fn dot(&self) -> Dot {
+        Dot(*self)
+    }
+
+    /// Renders the schedules that run at least one system as a text
+    /// table, in the order they run.
+    pub fn table(&self) -> Table {
+        Table(*self)
+    }
+
+    /// Bytes of RAM taken by the collection and the shared data, i.e. by an
+    /// `App` without its instrumentation.
+    pub const fn total_size(&self) -> usize {
+        self.collection.size + self.shared_data.size
+    }
+
+    /// Renders the size and alignment of every plugin, of the shared data,
+    /// and their total, as a text table.
+    pub fn footprint(&self) -> FootprintTable {
+        FootprintTable(*self)
+    }
+}
+
+/// Fails (at compile time, in a `const`) if the collection and the shared
+/// data described by `info` take more than `budget` bytes. Called by
+/// [`assert_footprint!`](crate::assert_footprint!).
+pub const fn check_footprint(info: CollectionInfo, budget: usize) {
+    if info.total_size() > budget {
+        panic!("the collection and its shared data exceed their memory budget");
+    }
+}
+
+/// Fails to compile if a generated collection and its shared data take
+/// more than a number of bytes:
+/// `assert_footprint!(GeneratedPluginCollection<Board>, 4096);`.
+///
+/// ```compile_fail,E0080
+/// use typed_ecs::{
+///     assert_footprint, macros::generate_collection, plugin::Plugin,
+///     shared_data::SharedData,
+/// };
+///
+/// struct Board;
+///
+/// impl SharedData for Board {
+///     fn build() -> Self {
+///         Self
+///     }
+/// }
+///
+/// struct FramebufferPlugin {
+///     pixels: [u8; 128 * 64],
+/// }
+///
+/// impl Plugin<Board> for FramebufferPlugin {
+///     fn build() -> Self {
+///         Self { pixels: [0; 128 * 64] }
+///     }
+/// }
+///
+/// generate_collection!(FramebufferPlugin);
+///
+/// // error[E0080]: the collection and its shared data exceed their memory
+/// // budget
+/// assert_footprint!(GeneratedPluginCollection<Board>, 4096);
+/// ```
+#[macro_export]
+macro_rules! assert_footprint {
+    ($collection:ty, $budget:expr) => {
+        const _: () = $crate::introspection::check_footprint(
+            <$collection as $crate::plugin_collection::PluginCollection<_>>::INFO,
+            $budget,
+        );
+    };
+}
+
+/// See [`CollectionInfo::dot`].
+pub struct Dot(CollectionInfo);
+
+impl fmt::Display for Dot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.0;
+        writeln!(f, "digraph schedules {{")?;
+        writeln!(f, "    rankdir=LR;")?;
+        writeln!(f, "    node [shape=box, fontname=\"monospace\"];")?;
+
+        for (idx, schedule) in SCHEDULES.iter().enumerate() {
+            let execution = info.execution[idx];
+            writeln!(
+                f,
+                "    s{idx} [label=\"{}\\n({})\", shape=ellipse];",
+                schedule.name,
+                execution.as_str()
+            )?;
+
+            // Sequential systems are chained, the others fan out of their
+            // schedule.
+            let mut previous = None;
+            for (plugin_idx, plugin) in info.plugins.iter().enumerate() {
+                if !plugin.systems.contains(idx) {
+                    continue;
+                }
+                writeln!(
+                    f,
+                    "    s{idx}_p{plugin_idx} [label=\"{}::{}\"];",
+                    plugin.name, schedule.system
+                )?;
+                match (execution, previous) {
+                    (Execution::Sequential, Some(previous)) => {
+                        writeln!(f, "    s{idx}_p{previous} -> s{idx}_p{plugin_idx};")?
+                    }
+                    _ => writeln!(f, "    s{idx} -> s{idx}_p{plugin_idx};")?,
+                }
+                previous = Some(plugin_idx);
+            }
+
+            // Then the last system (or all of them, when not sequential)
+            // leads to the next schedules.
+            for (next, attributes) in successors(idx).into_iter().flatten() {
+                match (execution, previous) {
+                    (_, None) => writeln!(f, "    s{idx} -> s{next}{attributes};")?,
+                    (Execution::Sequential, Some(last)) => {
+                        writeln!(f, "    s{idx}_p{last} -> s{next}{attributes};")?
+                    }
+                    _ => {
+                        for (plugin_idx, _) in info
+                            .plugins
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, plugin)| plugin.systems.contains(idx))
+                        {
+                            writeln!(f, "    s{idx}_p{plugin_idx} -> s{next}{attributes};")?;
+                        }
+                    }
+                }
+            }
+        }
+        // The plugins that may run in any schedule stand apart.
+        for (plugin_idx, plugin) in info.plugins.iter().enumerate() {
+            if !plugin.systems.is_known() {
+                writeln!(
+                    f,
+                    "    unknown_p{plugin_idx} [label=\"{}\\n(unknown systems)\", style=dashed];",
+                    plugin.name
+                )?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Schedules run after `SCHEDULES[idx]`, with the attributes of the edge.
+fn successors(idx: usize) -> [Option<(usize, &'static str)>; 2] {
+    const EXIT: &str = " [style=dashed, label=\"exit\"]";
+    const NEXT_FRAME: &str = " [style=dashed, label=\"next frame\"]";
+    match idx {
+        // ApplyAsyncPostUpdate, ExitCheck, then either OnExit or
+        // AsyncUpdate.
+        13 => [Some((EXIT_CHECK, "")), None],
+        EXIT_CHECK => [Some((14, "")), Some((ON_EXIT, EXIT))],
+        // ApplyAsyncUpdate loops back.
+        15 => [Some((FIRST_LOOP_SCHEDULE, NEXT_FRAME)), None],
+        ON_EXIT => [None, None],
+        _ => [Some((idx + 1, "")), None],
+    }
+}
+
+/// See [`CollectionInfo::table`].
+pub struct Table(CollectionInfo);
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.0;
+        writeln!(f, "{:<22} {:<11} Plugins", "Schedule", "Execution")?;
+        for idx in RUN_ORDER {
+            let schedule = &SCHEDULES[idx];
+            let mut plugins = info.plugins_in(idx).peekable();
+            if plugins.peek().is_none() {
+                continue;
+            }
+            write!(
+                f,
+                "{:<22} {:<11} ",
+                schedule.name,
+                info.execution[idx].as_str()
+            )?;
+            write_names(f, plugins)?;
+        }
+        // Then the plugins that may run in any of them.
+        let mut unknown = info.unknown_plugins().peekable();
+        if unknown.peek().is_some() {
+            write!(f, "{:<22} {:<11} ", "unknown", "-")?;
+            write_names(f, unknown)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_names<'a>(
+    f: &mut fmt::Formatter<'_>,
+    plugins: impl Iterator<Item = &'a PluginInfo>,
+) -> fmt::Result {
+    for (n, plugin) in plugins.enumerate() {
+        if n > 0 {
+            f.write_str(", ")?;
+        }
+        f.write_str(plugin.name)?;
+    }
+    writeln!(f)
+}
+
+/// See [`CollectionInfo::footprint`].
+pub struct FootprintTable(CollectionInfo);
+
+impl fmt::Display for FootprintTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = &self.0;
+        writeln!(f, "{:<24} {:>8} {:>6}", "Plugin", "Size", "Align")?;
+        for plugin in info.plugins {
+            writeln!(
+                f,
+                "{:<24} {:>8} {:>6}",
+                plugin.name, plugin.footprint.size, plugin.footprint.align
+            )?;
+        }
+        let plugins: usize = info.plugins.iter().map(|p| p.footprint.size).sum();
+        // Fields of the framework, and padding.
+        writeln!(
+            f,
+            "{:<24} {:>8}",
+            "(framework)",
+            info.collection.size - plugins
+        )?;
+        writeln!(
+            f,
+            "{:<24} {:>8} {:>6}",
+            "SharedData", info.shared_data.size, info.shared_data.align
+        )?;
+        writeln!(f, "{:<24} {:>8}", "Total", info.total_size())
+    }
+}
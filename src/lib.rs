@@ -4,14 +4,46 @@
 extern crate std;
 
 pub mod app;
+pub mod background;
+#[cfg(feature = "budget")]
+pub mod budget;
+pub mod dependencies;
 pub mod executor;
+#[cfg(feature = "fork-join")]
+pub mod fork_join;
 pub mod guard;
+pub mod idle;
+pub mod inject;
+pub mod instrumentation;
+pub mod introspection;
+pub mod logging;
+pub mod panic_isolation;
 pub mod plugin;
 pub mod plugin_collection;
 #[cfg(feature = "profile")]
 pub mod profile;
+#[cfg(feature = "profile-lite")]
+pub mod profile_lite;
+#[cfg(feature = "replay")]
+pub mod replay;
 pub mod shared_data;
 pub mod should_exit;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod static_app;
+#[cfg(feature = "system-stats")]
+pub mod system_stats;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "parallel")]
+pub mod thread_pool;
+#[cfg(feature = "async-timeout")]
+pub mod timeout;
+
+#[cfg(all(feature = "profile", feature = "profile-lite"))]
+compile_error!(
+    "`profile` and `profile-lite` both provide the collection hooks: enable only one of them."
+);
 
 pub use futures;
 #[cfg(feature = "parallel")]
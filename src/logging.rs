@@ -0,0 +1,434 @@
+//! Logging for plugins, with the name of the logging plugin attached to
+//! every record.
+//!
+//! The `error!`, `warn!`, `info!`, `debug!` and `trace!` macros of the
+//! crate are meant to be called from within a `Plugin` impl, whose
+//! `Plugin::NAME` names the record, or else its `core::any::type_name`
+//! (module path and generics included). The name is looked up through the
+//! shared data type of the impl, expected to be called `SD`, as in
+//! `impl<SD: SharedData> Plugin<SD>`. Otherwise, name it, or let it be
+//! inferred in an impl for a single shared data type:
+//! `info!(SD = Sensors; "...")`, or `info!(SD = _; "...")`.
+//!
+//! Records go to the global [`LogSink`]:
+//! - `LogCrateSink` forwards them to `log`, with the plugin as target
+//!   (`log` feature)
+//! - `TracingSink` emits them as `tracing` events, with a `plugin` field
+//!   (`profile` feature)
+//! - [`RingBuffer`] keeps the last ones in a fixed-size buffer, to drain
+//!   e.g. over a serial port, for `no_std` targets
+//! - any `LogSink` implementation, e.g. forwarding to `defmt`.
+//!
+//! ```rust
+//! use typed_ecs::{
+//!     logging::{self, Level, RingBuffer},
+//!     plugin::Plugin,
+//!     shared_data::{PhantomSharedData, SharedData},
+//! };
+//!
+//! static LOGS: RingBuffer<16> = RingBuffer::new();
+//!
+//! struct SensorPlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for SensorPlugin {
+//!     const NAME: &'static str = "sensors::SensorPlugin";
+//!
+//!     fn build() -> Self {
+//!         typed_ecs::info!("built");
+//!         Self
+//!     }
+//! }
+//!
+//! logging::set_sink(&LOGS).unwrap();
+//! let _plugin = <SensorPlugin as Plugin<PhantomSharedData>>::build();
+//!
+//! let mut entries = Vec::new();
+//! LOGS.drain(|entry| entries.push(*entry));
+//! assert_eq!(entries[0].plugin, "sensors::SensorPlugin");
+//! assert_eq!(entries[0].level, Level::Info);
+//! assert_eq!(entries[0].message(), "built");
+//! ```
+
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Write},
+};
+
+use portable_atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+use crate::{plugin::Plugin, shared_data::SharedData};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[repr(u8)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// A log record, as handed to the sink.
+pub struct Record<'a> {
+    /// `Plugin::NAME` of the logging plugin, or its type name, e.g.
+    /// `"sensors::SensorPlugin"`.
+    pub plugin: &'static str,
+    pub level: Level,
+    pub args: fmt::Arguments<'a>,
+}
+
+/// Destination of the records of every plugin.
+pub trait LogSink: Sync {
+    fn log(&self, record: &Record<'_>);
+}
+
+struct NopSink;
+
+impl LogSink for NopSink {
+    fn log(&self, _record: &Record<'_>) {}
+}
+
+const UNSET: u8 = 0;
+const SETTING: u8 = 1;
+const SET: u8 = 2;
+
+struct SinkCell(UnsafeCell<&'static dyn LogSink>);
+
+// SAFETY: written once, while `STATE` is SETTING, and only read once SET.
+unsafe impl Sync for SinkCell {}
+
+static STATE: AtomicU8 = AtomicU8::new(UNSET);
+static SINK: SinkCell = SinkCell(UnsafeCell::new(&NopSink));
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Returned by [`set_sink`] when a sink has already been set.
+#[derive(Debug)]
+pub struct SetSinkError;
+
+/// Sets the sink of the records. It can only be set once.
+pub fn set_sink(sink: &'static dyn LogSink) -> Result<(), SetSinkError> {
+    if STATE
+        .compare_exchange(UNSET, SETTING, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return Err(SetSinkError);
+    }
+    // SAFETY: we are the only writer, and no one reads before SET.
+    unsafe { *SINK.0.get() = sink };
+    STATE.store(SET, Ordering::Release);
+    Ok(())
+}
+
+/// The records less severe than `level` are discarded before being
+/// formatted. `Info` by default.
+pub fn set_max_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Called by the logging macros.
+#[doc(hidden)]
+pub fn log(plugin: &'static str, level: Level, args: fmt::Arguments<'_>) {
+    if STATE.load(Ordering::Acquire) != SET {
+        return;
+    }
+    // SAFETY: SET guarantees no more writes.
+    let sink = unsafe { *SINK.0.get() };
+    sink.log(&Record {
+        plugin,
+        level,
+        args,
+    });
+}
+
+/// `Plugin::NAME`, unless empty, or the type name. Called by the logging
+/// macros.
+#[doc(hidden)]
+pub fn plugin_name<P: Plugin<SD>, SD: SharedData>() -> &'static str {
+    crate::introspection::plugin_name(P::NAME, core::any::type_name::<P>())
+}
+
+/// Logs from within a `Plugin` impl, with the plugin's name attached.
+#[macro_export]
+macro_rules! log {
+    (SD = $sd:ty; $level:expr, $($arg:tt)+) => {
+        if $crate::logging::enabled($level) {
+            $crate::logging::log(
+                $crate::logging::plugin_name::<Self, $sd>(),
+                $level,
+                ::core::format_args!($($arg)+),
+            );
+        }
+    };
+    ($level:expr, $($arg:tt)+) => { $crate::log!(SD = SD; $level, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! error {
+    (SD = $sd:ty; $($arg:tt)+) => {
+        $crate::log!(SD = $sd; $crate::logging::Level::Error, $($arg)+)
+    };
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    (SD = $sd:ty; $($arg:tt)+) => {
+        $crate::log!(SD = $sd; $crate::logging::Level::Warn, $($arg)+)
+    };
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    (SD = $sd:ty; $($arg:tt)+) => {
+        $crate::log!(SD = $sd; $crate::logging::Level::Info, $($arg)+)
+    };
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    (SD = $sd:ty; $($arg:tt)+) => {
+        $crate::log!(SD = $sd; $crate::logging::Level::Debug, $($arg)+)
+    };
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    (SD = $sd:ty; $($arg:tt)+) => {
+        $crate::log!(SD = $sd; $crate::logging::Level::Trace, $($arg)+)
+    };
+    ($($arg:tt)+) => { $crate::log!($crate::logging::Level::Trace, $($arg)+) };
+}
+
+/// Forwards the records to the `log` crate, with the plugin name as their
+/// target, so they can be filtered per plugin (e.g. with `env_logger`,
+/// `RUST_LOG=SensorPlugin=debug`).
+#[cfg(feature = "log")]
+pub struct LogCrateSink;
+
+#[cfg(feature = "log")]
+impl LogSink for LogCrateSink {
+    fn log(&self, record: &Record<'_>) {
+        let level = match record.level {
+            Level::Error => log::Level::Error,
+            Level::Warn => log::Level::Warn,
+            Level::Info => log::Level::Info,
+            Level::Debug => log::Level::Debug,
+            Level::Trace => log::Level::Trace,
+        };
+        log::logger().log(
+            &log::Record::builder()
+                .level(level)
+                .target(record.plugin)
+                .args(record.args)
+                .build(),
+        );
+    }
+}
+
+/// Emits the records as `tracing` events, with a `plugin` field.
+#[cfg(feature = "profile")]
+pub struct TracingSink;
+
+#[cfg(feature = "profile")]
+impl LogSink for TracingSink {
+    fn log(&self, record: &Record<'_>) {
+        let (plugin, args) = (record.plugin, record.args);
+        match record.level {
+            Level::Error => tracing::error!(plugin, "{args}"),
+            Level::Warn => tracing::warn!(plugin, "{args}"),
+            Level::Info => tracing::info!(plugin, "{args}"),
+            Level::Debug => tracing::debug!(plugin, "{args}"),
+            Level::Trace => tracing::trace!(plugin, "{args}"),
+        }
+    }
+}
+
+/// A record stored in a [`RingBuffer`], its message truncated to `M`
+/// bytes.
+#[derive(Clone, Copy)]
+pub struct LogEntry<const M: usize> {
+    pub plugin: &'static str,
+    pub level: Level,
+    len: usize,
+    message: [u8; M],
+}
+
+impl<const M: usize> LogEntry<M> {
+    const EMPTY: Self = Self {
+        plugin: "",
+        level: Level::Trace,
+        len: 0,
+        message: [0; M],
+    };
+
+    pub fn message(&self) -> &str {
+        // Truncation only happens on char boundaries.
+        core::str::from_utf8(&self.message[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const M: usize> fmt::Debug for LogEntry<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{} {}] {}", self.level, self.plugin, self.message())
+    }
+}
+
+impl<const M: usize> Write for LogEntry<M> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut end = s.len().min(M - self.len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.message[self.len..self.len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end;
+        Ok(())
+    }
+}
+
+/// Allocation-free sink keeping the last `N` records, each message
+/// truncated to `M` bytes. When full, the oldest records are overwritten
+/// (and counted by `RingBuffer::overwritten`).
+///
+/// It never blocks, so plugins may log from any context, interrupt
+/// handlers included: a record pushed while the buffer is busy in another
+/// context (the code it interrupted, or another core) is dropped, and
+/// counted by `RingBuffer::dropped`. Likewise, a drain stops early.
+pub struct RingBuffer<const N: usize, const M: usize = 64> {
+    locked: AtomicBool,
+    ring: UnsafeCell<Ring<N, M>>,
+    overwritten: AtomicU32,
+    dropped: AtomicU32,
+}
+
+struct Ring<const N: usize, const M: usize> {
+    entries: [LogEntry<M>; N],
+    head: usize,
+    len: usize,
+}
+
+// SAFETY: `ring` is only accessed by whoever set `locked`.
+unsafe impl<const N: usize, const M: usize> Sync for RingBuffer<N, M> {}
+
+impl<const N: usize, const M: usize> Default for RingBuffer<N, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const M: usize> RingBuffer<N, M> {
+    pub const fn new() -> Self {
+        assert!(N > 0, "a RingBuffer needs room for at least one record");
+        Self {
+            locked: AtomicBool::new(false),
+            ring: UnsafeCell::new(Ring {
+                entries: [LogEntry::EMPTY; N],
+                head: 0,
+                len: 0,
+            }),
+            overwritten: AtomicU32::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Stores `entry`, unless the buffer is busy in another context.
+    pub fn push(&self, entry: LogEntry<M>) {
+        let overwritten = self.try_with_ring(|ring| {
+            let tail = (ring.head + ring.len) % N;
+            ring.entries[tail] = entry;
+            if ring.len == N {
+                ring.head = (ring.head + 1) % N;
+                true
+            } else {
+                ring.len += 1;
+                false
+            }
+        });
+        match overwritten {
+            Some(false) => {}
+            Some(true) => {
+                self.overwritten.fetch_add(1, Ordering::Relaxed);
+            }
+            None => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Hands the stored records to `f`, oldest first, and empties the
+    /// buffer. Stops early if the buffer is busy in another context.
+    pub fn drain(&self, mut f: impl FnMut(&LogEntry<M>)) {
+        loop {
+            let entry = self.try_with_ring(|ring| {
+                if ring.len == 0 {
+                    return None;
+                }
+                let entry = ring.entries[ring.head];
+                ring.head = (ring.head + 1) % N;
+                ring.len -= 1;
+                Some(entry)
+            });
+            match entry.flatten() {
+                Some(entry) => f(&entry),
+                None => return,
+            }
+        }
+    }
+
+    /// Number of records lost, the buffer being full.
+    pub fn overwritten(&self) -> u32 {
+        self.overwritten.load(Ordering::Relaxed)
+    }
+
+    /// Number of records lost, the buffer being busy in another context.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f` on the ring, unless another context is at it.
+    fn try_with_ring<R>(&self, f: impl FnOnce(&mut Ring<N, M>) -> R) -> Option<R> {
+        if self.locked.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: `locked` was unset: the ring is ours until we unset it.
+        let result = f(unsafe { &mut *self.ring.get() });
+        self.locked.store(false, Ordering::Release);
+        Some(result)
+    }
+}
+
+impl<const N: usize, const M: usize> LogSink for RingBuffer<N, M> {
+    fn log(&self, record: &Record<'_>) {
+        // Formatted before claiming the ring.
+        let mut entry = LogEntry::EMPTY;
+        entry.plugin = record.plugin;
+        entry.level = record.level;
+        let _ = entry.write_fmt(record.args);
+        self.push(entry);
+    }
+}
@@ -0,0 +1,159 @@
+/// What the collection does with a plugin whose system panicked, when the
+/// `panic-isolation` feature is enabled (see `Plugin::ON_PANIC`). Without
+/// the feature, a panic unwinds through the whole loop.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnPanic {
+    /// Replaces the plugin with a fresh one, from `Plugin::build`. Note
+    /// that the startup systems aren't run again.
+    Rebuild,
+    /// Poisons the plugin: its systems (OnExit included) are skipped for
+    /// the rest of the run.
+    Skip,
+    /// Poisons the plugin, and requests exit at the next ExitCheck, with
+    /// `ExitReason::PluginPanicked`.
+    Exit,
+}
+
+#[cfg(feature = "panic-isolation")]
+pub use isolation::*;
+
+#[cfg(feature = "panic-isolation")]
+mod isolation {
+    use core::{
+        future::Future,
+        panic::AssertUnwindSafe,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+    use std::{
+        any::Any,
+        boxed::Box,
+        string::{String, ToString},
+        sync::Mutex,
+    };
+
+    use futures::FutureExt;
+
+    use super::OnPanic;
+    use crate::{plugin::Plugin, shared_data::SharedData};
+
+    type Payload = Box<dyn Any + Send + 'static>;
+
+    /// Which system panicked, reported by `App::take_panic`, and in
+    /// `ExitReason::PluginPanicked`.
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub struct PanicReport {
+        pub schedule: &'static str,
+        pub plugin: &'static str,
+        pub system: &'static str,
+        /// The panic payload, when it is a string (as with `panic!`).
+        pub message: String,
+    }
+
+    /// Panic state of a collection of `N` plugins, maintained by the
+    /// generated code.
+    pub struct Isolation<const N: usize> {
+        poisoned: [AtomicBool; N],
+        // Set along `Reports::exit`, so that ExitCheck doesn't lock.
+        exit_requested: AtomicBool,
+        reports: Mutex<Reports>,
+    }
+
+    struct Reports {
+        // First panic not taken yet, whatever the policy.
+        first: Option<PanicReport>,
+        // First panic of a plugin whose policy is `OnPanic::Exit`.
+        exit: Option<PanicReport>,
+    }
+
+    impl<const N: usize> Default for Isolation<N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<const N: usize> Isolation<N> {
+        pub const fn new() -> Self {
+            Self {
+                poisoned: [const { AtomicBool::new(false) }; N],
+                exit_requested: AtomicBool::new(false),
+                reports: Mutex::new(Reports {
+                    first: None,
+                    exit: None,
+                }),
+            }
+        }
+
+        #[inline(always)]
+        pub fn is_poisoned(&self, plugin: usize) -> bool {
+            self.poisoned[plugin].load(Ordering::Relaxed)
+        }
+
+        /// Applies `P::ON_PANIC` to the plugin of index `idx`, and keeps
+        /// the report of the panic.
+        pub fn handle_panic<SD: SharedData, P: Plugin<SD>>(
+            &self,
+            idx: usize,
+            plugin: &mut P,
+            (schedule, plugin_name, system): (&'static str, &'static str, &'static str),
+            payload: Payload,
+        ) {
+            match P::ON_PANIC {
+                OnPanic::Rebuild => *plugin = P::build(),
+                OnPanic::Skip | OnPanic::Exit => self.poisoned[idx].store(true, Ordering::Relaxed),
+            }
+            let report = PanicReport {
+                schedule,
+                plugin: plugin_name,
+                system,
+                message: payload_message(payload.as_ref()),
+            };
+            let mut reports = self.reports.lock().unwrap_or_else(|e| e.into_inner());
+            if P::ON_PANIC == OnPanic::Exit && reports.exit.is_none() {
+                reports.exit = Some(report.clone());
+                self.exit_requested.store(true, Ordering::Relaxed);
+            }
+            reports.first.get_or_insert(report);
+        }
+
+        #[inline(always)]
+        pub fn exit_requested(&self) -> bool {
+            self.exit_requested.load(Ordering::Relaxed)
+        }
+
+        /// Takes the report of the first panic since the last call.
+        pub fn take_panic(&mut self) -> Option<PanicReport> {
+            self.reports_mut().first.take()
+        }
+
+        /// Takes the report of the panic that requested exit.
+        pub fn take_exit_panic(&mut self) -> Option<PanicReport> {
+            self.reports_mut().exit.take()
+        }
+
+        fn reports_mut(&mut self) -> &mut Reports {
+            self.reports.get_mut().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    fn payload_message(payload: &(dyn Any + Send)) -> String {
+        if let Some(message) = payload.downcast_ref::<&'static str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        }
+    }
+
+    /// Runs a sync system, catching its panic.
+    #[inline(always)]
+    pub fn catch(system: impl FnOnce()) -> Result<(), Payload> {
+        std::panic::catch_unwind(AssertUnwindSafe(system))
+    }
+
+    /// Awaits an async system, catching its panic.
+    #[inline(always)]
+    pub async fn catch_future(system: impl Future<Output = ()>) -> Result<(), Payload> {
+        AssertUnwindSafe(system).catch_unwind().await
+    }
+}
@@ -1,8 +1,47 @@
 #![allow(async_fn_in_trait)]
 
-use crate::{shared_data::SharedData, should_exit::ShouldExit};
+use crate::{
+    idle::Idle, introspection::Systems, panic_isolation::OnPanic, shared_data::SharedData,
+    should_exit::ShouldExit,
+};
 
 pub trait Plugin<SD: SharedData> {
+    /// Name of the plugin in traces, statistics, introspection and
+    /// reports. Empty (the default) stands for the type, as written in
+    /// `generate_collection!`: set it to tell apart plugins of the same
+    /// type name, e.g. `"physics::Renderer"`.
+    const NAME: &'static str = "";
+    /// Version of the plugin, e.g. `env!("CARGO_PKG_VERSION")`. Empty by
+    /// default.
+    const VERSION: &'static str = "";
+    /// One-line description of the plugin. Empty by default.
+    const DESCRIPTION: &'static str = "";
+
+    /// Deadline of each async system of this plugin, and of its async
+    /// task, awaited with the collection's `Timer`. `None` (the default)
+    /// waits forever. Only applies with the `async-timeout` feature.
+    #[cfg(feature = "async-timeout")]
+    const ASYNC_TIMEOUT: Option<core::time::Duration> = None;
+    /// What to do with an async task exceeding `ASYNC_TIMEOUT`.
+    #[cfg(feature = "async-timeout")]
+    const ON_ASYNC_TIMEOUT: crate::timeout::OnTimeout = crate::timeout::OnTimeout::Cancel;
+    /// What to do when one of the systems of this plugin panics. Only
+    /// applies with the `panic-isolation` feature.
+    const ON_PANIC: OnPanic = OnPanic::Exit;
+    /// Systems this plugin implements, shown by the introspection of its
+    /// collection (see `introspection`). Filled in by the
+    /// `#[typed_ecs::macros::systems]` attribute: unknown otherwise.
+    const SYSTEMS: Systems = Systems::UNKNOWN;
+
+    /// Time budget of a system of this plugin, by name (e.g. `"update"`),
+    /// checked with the collection's `Watchdog`. `None` (the default)
+    /// disables the check. Only applies with the `budget` feature.
+    #[cfg(feature = "budget")]
+    #[inline(always)]
+    fn budget(_system: &'static str) -> Option<core::time::Duration> {
+        None
+    }
+
     // Methods are in their order of execution
 
     // APP INIT - PRE STARTUP
@@ -19,6 +58,22 @@ pub trait Plugin<SD: SharedData> {
     #[inline(always)]
     fn apply_async_startup(&mut self, _sd: &mut SD) {}
 
+    // BACKGROUND - created once after startup, then polled once per frame
+    // and alongside the async schedules, until the app exits. The task
+    // can't borrow the plugin: each of its results is a closure, applied to
+    // the plugin and the SharedData right before the ApplyAsyncUpdate
+    // schedule (one per frame, the task waiting meanwhile).
+    // `background-tasks` feature.
+
+    #[cfg(feature = "background-tasks")]
+    #[inline(always)]
+    fn background_task(
+        &mut self,
+    ) -> impl futures::Stream<Item = impl FnOnce(&mut Self, &mut SD) + use<Self, SD>> + use<Self, SD>
+    {
+        futures::stream::empty::<fn(&mut Self, &mut SD)>()
+    }
+
     // LOOP - UPDATES
 
     #[inline(always)]
@@ -26,6 +81,11 @@ pub trait Plugin<SD: SharedData> {
     #[inline(always)]
     fn apply_pre_update(&mut self, _sd: &mut SD) {}
 
+    #[inline(always)]
+    async fn async_pre_update(&mut self, _sd: &SD) {}
+    #[inline(always)]
+    fn apply_async_pre_update(&mut self, _sd: &mut SD) {}
+
     #[inline(always)]
     fn update(&mut self, _sd: &SD) {}
     #[inline(always)]
@@ -36,16 +96,87 @@ pub trait Plugin<SD: SharedData> {
     #[inline(always)]
     fn apply_post_update(&mut self, _sd: &mut SD) {}
 
+    #[inline(always)]
+    async fn async_post_update(&mut self, _sd: &SD) {}
+    #[inline(always)]
+    fn apply_async_post_update(&mut self, _sd: &mut SD) {}
+
+    #[inline(always)]
+    fn exit_check<S: ShouldExit>(&mut self, _should_exit: &mut S, _sd: &SD) {}
+
     #[inline(always)]
     async fn async_update(&mut self, _sd: &SD) {}
     #[inline(always)]
     fn apply_async_update(&mut self, _sd: &mut SD) {}
 
+    // ASYNC TASK - started in the AsyncUpdate schedule, alongside
+    // `async_update`, unless the previous one is still pending. Unlike the
+    // future of an async system, it owns what it awaits, so it can outlive
+    // its frame (see `OnTimeout::CarryOver`). Its result is applied to the
+    // plugin and the SharedData right before the ApplyAsyncUpdate schedule
+    // of the frame it completes in. `background-tasks` feature.
+
+    #[cfg(feature = "background-tasks")]
     #[inline(always)]
-    fn exit_check<S: ShouldExit>(&mut self, _should_exit: &mut S, _sd: &SD) {}
+    fn async_task(
+        &mut self,
+        _sd: &SD,
+    ) -> Option<
+        impl core::future::Future<Output = impl FnOnce(&mut Self, &mut SD) + use<Self, SD>>
+        + use<Self, SD>,
+    > {
+        None::<core::future::Ready<fn(&mut Self, &mut SD)>>
+    }
 
     // SHUTDOWN (runs once)
 
     #[inline(always)]
     fn on_exit(&mut self, _sd: &SD) {}
+
+    // ASYNC TIMEOUTS (runs after an async system exceeded ASYNC_TIMEOUT,
+    // or before ApplyAsyncUpdate for the async task, `async-timeout`
+    // feature)
+
+    #[cfg(feature = "async-timeout")]
+    #[inline(always)]
+    fn on_async_timeout(&mut self, _system: &'static str, _sd: &SD) {}
+
+    // IDLE (runs at the end of every frame, with `idle::IdleExecutor`)
+
+    /// Whether this plugin has work for the next frame. Busy by default:
+    /// the app only sleeps when every plugin reports being idle.
+    #[inline(always)]
+    fn idle(&self, _sd: &SD) -> Idle {
+        Idle::Busy
+    }
+
+    // SYSTEM STATS (runs at the end of every frame, `system-stats` feature)
+
+    #[cfg(feature = "system-stats")]
+    #[inline(always)]
+    fn on_system_stats(&mut self, _stats: &crate::system_stats::SystemStats<'_>, _sd: &SD) {}
+
+    // SNAPSHOTS (`serde` feature, see `App::save_snapshot`)
+
+    /// Saves the state of this plugin into a snapshot. Nothing is saved
+    /// by default.
+    #[cfg(feature = "serde")]
+    #[inline(always)]
+    fn save_state(
+        &self,
+        _out: &mut crate::snapshot::SnapshotWriter<'_>,
+    ) -> Result<(), crate::snapshot::SnapshotError> {
+        Ok(())
+    }
+
+    /// Decodes what `Plugin::save_state` saved, and returns what restores
+    /// it: it's only called once the whole snapshot decoded, for a corrupt
+    /// one to leave the plugin untouched.
+    #[cfg(feature = "serde")]
+    #[inline(always)]
+    fn load_state(
+        _input: &mut crate::snapshot::SnapshotReader<'_>,
+    ) -> Result<impl FnOnce(&mut Self) + use<Self, SD>, crate::snapshot::SnapshotError> {
+        Ok(|_: &mut Self| {})
+    }
 }
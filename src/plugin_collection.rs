@@ -1,6 +1,11 @@
 #![allow(async_fn_in_trait)]
 
-use crate::{shared_data::SharedData, should_exit::ShouldExit};
+use core::pin::Pin;
+
+use crate::{
+    background::CollectionTasks, instrumentation::Instrumentation, shared_data::SharedData,
+    should_exit::ShouldExit,
+};
 
 /// The generated PluginCollection implements this trait. The SharedData
 /// constraints are local to each plugin, and the SharedData of the collection
@@ -12,84 +17,127 @@ use crate::{shared_data::SharedData, should_exit::ShouldExit};
 pub trait PluginCollection<SD: SharedData> {
     const PLUGIN_NUM: usize;
 
+    /// Plugins of the collection, their systems, and how the schedules
+    /// run them (see `introspection`).
+    const INFO: crate::introspection::CollectionInfo;
+
+    /// Backend the non-applying schedules are forked on.
+    #[cfg(feature = "fork-join")]
+    type ForkJoin: crate::fork_join::ForkJoin;
+
+    #[cfg(feature = "fork-join")]
+    fn fork_join(&self) -> &Self::ForkJoin;
+
+    /// Replaces the fork-join backend. See `App::with_fork_join`.
+    #[cfg(feature = "fork-join")]
+    fn set_fork_join(&mut self, fork_join: Self::ForkJoin);
+
     // STARTUP
-    fn startup_all(&mut self, _sd: &SD);
-    fn apply_startup_all(&mut self, _sd: &mut SD);
+    fn startup_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_startup_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
+
+    async fn async_startup_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_async_startup_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
+
+    /// Takes the report of the first panic since the last call, whatever
+    /// the `OnPanic` policy of the plugin.
+    #[cfg(feature = "panic-isolation")]
+    fn take_panic(&mut self) -> Option<crate::panic_isolation::PanicReport>;
+
+    /// Takes the report of the panic that requested exit, if any.
+    #[cfg(feature = "panic-isolation")]
+    fn take_exit_panic(&mut self) -> Option<crate::panic_isolation::PanicReport>;
+
+    /// Takes the budget violation that requested exit, if any.
+    #[cfg(feature = "budget")]
+    fn take_budget_violation(&mut self) -> Option<crate::budget::BudgetViolation>;
+
+    /// Number of budgets exceeded so far.
+    #[cfg(feature = "budget")]
+    fn budget_violations(&self) -> u32;
 
-    async fn async_startup_all(&mut self, _sd: &SD);
-    fn apply_async_startup_all(&mut self, _sd: &mut SD);
+    /// Timing statistics of the plugins systems and of the schedules.
+    #[cfg(feature = "system-stats")]
+    fn system_stats(&self) -> crate::system_stats::SystemStats<'_>;
+
+    /// Hands the statistics to every plugin (`Plugin::on_system_stats`).
+    #[cfg(feature = "system-stats")]
+    fn system_stats_all(&mut self, _sd: &SD);
+
+    /// Fingerprint of the plugins of the collection, checked when
+    /// loading a snapshot.
+    #[cfg(feature = "serde")]
+    const SNAPSHOT_FINGERPRINT: u64;
+
+    /// Saves the state of every plugin (`Plugin::save_state`).
+    #[cfg(feature = "serde")]
+    fn save_state_all(
+        &self,
+        _out: &mut crate::snapshot::SnapshotWriter<'_>,
+    ) -> Result<(), crate::snapshot::SnapshotError>;
+
+    /// Decodes the shared data and the state of every plugin
+    /// (`Plugin::load_state`), and restores them once all decoded.
+    #[cfg(feature = "serde")]
+    fn load_state_all(
+        &mut self,
+        _input: &mut crate::snapshot::SnapshotReader<'_>,
+        _shared_data: &mut SD,
+    ) -> Result<(), crate::snapshot::SnapshotError>
+    where
+        SD: crate::snapshot::Snapshot;
+
+    /// Combines the idle reports of every plugin (`Plugin::idle`).
+    fn idle_all(&self, _sd: &SD) -> crate::idle::Idle;
+
+    // BACKGROUND
+
+    /// Creates the background task of every plugin, and the slots of their
+    /// async tasks.
+    fn background_tasks_all(&mut self) -> impl CollectionTasks<SD, Self> + use<Self, SD>;
 
     // LOOP - UPDATES
 
-    fn pre_update_all(&mut self, _sd: &SD);
-    fn apply_pre_update_all(&mut self, _sd: &mut SD);
+    fn pre_update_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_pre_update_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
+
+    async fn async_pre_update_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_async_pre_update_all<I: Instrumentation>(
+        &mut self,
+        _sd: &mut SD,
+        _instrumentation: &I,
+    );
+
+    fn update_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_update_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
 
-    fn update_all(&mut self, _sd: &SD);
-    fn apply_update_all(&mut self, _sd: &mut SD);
+    fn post_update_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_post_update_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
 
-    fn post_update_all(&mut self, _sd: &SD);
-    fn apply_post_update_all(&mut self, _sd: &mut SD);
+    async fn async_post_update_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
+    fn apply_async_post_update_all<I: Instrumentation>(
+        &mut self,
+        _sd: &mut SD,
+        _instrumentation: &I,
+    );
 
-    async fn async_update_all(&mut self, _sd: &SD);
-    fn apply_async_update_all(&mut self, _sd: &mut SD);
+    fn exit_check_all<S: ShouldExit, I: Instrumentation>(
+        &mut self,
+        _should_exit: &mut S,
+        _sd: &SD,
+        _instrumentation: &I,
+    );
 
-    fn exit_check_all<S: ShouldExit>(&mut self, _should_exit: &mut S, _sd: &SD);
+    /// Also waits for the async tasks `tasks` started.
+    async fn async_update_all<I: Instrumentation>(
+        &mut self,
+        _sd: &SD,
+        _instrumentation: &I,
+        _tasks: Pin<&mut dyn CollectionTasks<SD, Self>>,
+    );
+    fn apply_async_update_all<I: Instrumentation>(&mut self, _sd: &mut SD, _instrumentation: &I);
 
     // SHUTDOWN (runs once)
 
-    fn on_exit_all(&mut self, _sd: &SD);
-
-    // ------------------------------------------------------------
-    // METHODS, THE DEFAULT IMPLEMENTATION OF WHOSE SHOULD NEVER BE
-    // REDEFINED BY THE USER, OR ELSE THE PROGRAM WILL NOT BEHAVE
-    // AS THE LIB EXPECTS, POSSIBLY LEADING TO LOGICAL UNDEFINED
-    // BEHAVIOR, THAT'S WHY THE TRAIT MUST BE IMPLEMENTED BY MACRO!
-    // ------------------------------------------------------------
-
-    /// Hook, called individually once per schedule (e.g. on the
-    /// beginning of Startup, Update, ...).
-    ///
-    /// Used for:
-    /// - profiling: `profile` feature
-    ///
-    /// When all of the features listed above are disabled, the
-    /// function gets optimized away, as it returns a ZST.
-    #[inline(always)]
-    fn on_schedule_start(_schedule: &'static str) -> impl Drop {
-        #[cfg(feature = "profile")]
-        {
-            let guard = tracing::info_span!("ecs_schedule", schedule = %_schedule).entered();
-            return guard;
-        }
-        #[cfg(not(feature = "profile"))]
-        {
-            use crate::guard::NoopGuard;
-            NoopGuard
-        }
-    }
-
-    /// Hook, called individually once per plugin system call.
-    ///
-    /// Used for:
-    /// - profiling: `profile` feature
-    ///
-    /// When all of the features listed above are disabled, the
-    /// function gets optimized away, as it returns a ZST.
-    #[inline(always)]
-    fn on_system_start(
-        _schedule: &'static str,
-        _plugin: &'static str,
-        _system: &'static str,
-    ) -> impl Drop {
-        #[cfg(feature = "profile")]
-        {
-            let guard = tracing::info_span!("ecs_schedule", schedule = %_schedule, plugin = %_plugin, system = %_system).entered();
-            return guard;
-        }
-        #[cfg(not(feature = "profile"))]
-        {
-            use crate::guard::NoopGuard;
-            NoopGuard
-        }
-    }
+    fn on_exit_all<I: Instrumentation>(&mut self, _sd: &SD, _instrumentation: &I);
 }
@@ -1,5 +1,10 @@
 pub use tracing;
 
+use crate::instrumentation::Instrumentation;
+
+#[cfg(feature = "profile-chrome")]
+pub mod chrome;
+
 /// Sets up a global default subscriber, chosen with enabled features
 /// You must not set another global default subscriber, or else the
 /// program can either crash, either ignore the second init.
@@ -17,12 +22,57 @@ pub fn setup_default_profiling() {
     {
         tracing_forest::init();
     }
+    #[cfg(feature = "profile-chrome")]
+    {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry()
+                .with(chrome::ChromeLayer::new("trace.json").expect("create trace.json")),
+        )
+        .expect("setup chrome layer");
+    }
     #[cfg(all(feature = "profile-forest", feature = "profile-tracy"))]
     compile_error!(
         "You cannot enable two profilers at the same time! Enable only one `profile-...` feature!\nCurrently enabled: `profile-tracy` AND `profile-forest`."
     );
-    #[cfg(not(any(feature = "profile-forest", feature = "profile-tracy")))]
+    #[cfg(all(feature = "profile-chrome", feature = "profile-tracy"))]
+    compile_error!(
+        "You cannot enable two profilers at the same time! Enable only one `profile-...` feature!\nCurrently enabled: `profile-tracy` AND `profile-chrome`."
+    );
+    #[cfg(all(feature = "profile-chrome", feature = "profile-forest"))]
+    compile_error!(
+        "You cannot enable two profilers at the same time! Enable only one `profile-...` feature!\nCurrently enabled: `profile-forest` AND `profile-chrome`."
+    );
+    #[cfg(not(any(
+        feature = "profile-forest",
+        feature = "profile-tracy",
+        feature = "profile-chrome"
+    )))]
     compile_error!(
-        "You should enable either feature `profile-forest` (zero-setup, built-in profiler), \neither `profile-tracy` (requires Tracy - an external application) enabled to build this example.\nExample usage: cargo run --example profile --features=profile-forest"
+        "You should enable either feature `profile-forest` (zero-setup, built-in profiler), \neither `profile-chrome` (writes a `trace.json`, to open in Perfetto or chrome://tracing), \neither `profile-tracy` (requires Tracy - an external application) enabled to build this example.\nExample usage: cargo run --example profile --features=profile-forest"
     );
 }
+
+/// Instrumentation opening a `tracing` span named `ecs_schedule` for every
+/// schedule and every system, with `schedule`, `plugin` and `system`
+/// fields. The default instrumentation with the `profile` feature.
+#[derive(Default, Clone, Copy)]
+pub struct Tracing;
+
+impl Instrumentation for Tracing {
+    #[inline(always)]
+    fn on_schedule_start(&self, schedule: &'static str) -> impl Drop {
+        tracing::info_span!("ecs_schedule", schedule = %schedule).entered()
+    }
+
+    #[inline(always)]
+    fn on_system_start(
+        &self,
+        schedule: &'static str,
+        plugin: &'static str,
+        system: &'static str,
+    ) -> impl Drop {
+        tracing::info_span!("ecs_schedule", schedule = %schedule, plugin = %plugin, system = %system).entered()
+    }
+}
@@ -0,0 +1,228 @@
+use core::{
+    cell::Cell,
+    fmt::{self, Write as _},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    string::String,
+    sync::Mutex,
+    time::Instant,
+};
+
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+    span,
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+/// `tracing` layer writing a Chrome trace-event JSON file (`profile-chrome`
+/// feature), viewable in Perfetto (<https://ui.perfetto.dev>) or
+/// `chrome://tracing`.
+///
+/// Every span becomes a duration slice on the track of the thread it has
+/// been entered on, so the systems run by parallel workers get their own
+/// tracks, named after their threads. The `ecs_schedule` spans of the
+/// collection are named after their fields: `Update` for a schedule,
+/// `Plugin::update` for a system, so nesting reads like the schedule.
+///
+/// The systems of the async schedules run concurrently on the same
+/// thread, so their spans can't nest: they are written as async slices,
+/// each on its own track, below the thread track.
+///
+/// The file is flushed every time a root span (such as the
+/// `Executor Runtime` span of `App::run`) is exited. The closing `]` is
+/// never written, which the trace-event format explicitly allows.
+pub struct ChromeLayer {
+    out: Mutex<BufWriter<File>>,
+    start: Instant,
+    pid: u32,
+}
+
+impl ChromeLayer {
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"[\n")?;
+        Ok(Self {
+            out: Mutex::new(out),
+            start: Instant::now(),
+            pid: std::process::id(),
+        })
+    }
+
+    fn write(&self, phase: char, name: &str, args: &str, id: Option<u64>, flush: bool) {
+        let ts = self.start.elapsed().as_nanos() as f64 / 1000.0;
+        let (tid, first_event) = thread_id();
+
+        let mut line = String::new();
+        if first_event {
+            let thread = std::thread::current();
+            let _ = write!(
+                line,
+                r#"{{"name":"thread_name","ph":"M","pid":{},"tid":{},"args":{{"name":"#,
+                self.pid, tid
+            );
+            escape(&mut line, thread.name().unwrap_or("unnamed"));
+            line.push_str("}},\n");
+        }
+        line.push_str(r#"{"name":"#);
+        escape(&mut line, name);
+        let _ = write!(
+            line,
+            r#","cat":"typed_ecs","ph":"{phase}","ts":{ts:.3},"pid":{},"tid":{tid}"#,
+            self.pid
+        );
+        if phase == 'i' {
+            line.push_str(r#","s":"t""#);
+        }
+        if let Some(id) = id {
+            let _ = write!(line, r#","id":{id}"#);
+        }
+        if !args.is_empty() {
+            let _ = write!(line, r#","args":{{{args}}}"#);
+        }
+        line.push_str("},\n");
+
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = out.write_all(line.as_bytes());
+        if flush {
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Name and JSON arguments of a span, computed once on creation.
+struct ChromeSpan {
+    name: String,
+    args: String,
+    // System of an async schedule.
+    is_async: bool,
+}
+
+impl<S> Layer<S> for ChromeLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        let is_async = fields.plugin.is_some()
+            && fields
+                .schedule
+                .as_deref()
+                .is_some_and(|schedule| schedule.starts_with("Async"));
+        let name = match (fields.schedule, fields.plugin, fields.system) {
+            (_, Some(plugin), Some(system)) => std::format!("{plugin}::{system}"),
+            (Some(schedule), None, None) => schedule,
+            _ => String::from(attrs.metadata().name()),
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(ChromeSpan {
+                name,
+                args: fields.args,
+                is_async,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(chrome) = span.extensions().get::<ChromeSpan>()
+        {
+            if chrome.is_async {
+                self.write('b', &chrome.name, &chrome.args, Some(id.into_u64()), false);
+            } else {
+                self.write('B', &chrome.name, &chrome.args, None, false);
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(chrome) = span.extensions().get::<ChromeSpan>()
+        {
+            let flush = span.parent().is_none();
+            if chrome.is_async {
+                self.write('e', &chrome.name, "", Some(id.into_u64()), flush);
+            } else {
+                self.write('E', &chrome.name, "", None, flush);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let name = fields
+            .message
+            .unwrap_or_else(|| String::from(event.metadata().name()));
+        self.write('i', &name, &fields.args, None, false);
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    schedule: Option<String>,
+    plugin: Option<String>,
+    system: Option<String>,
+    message: Option<String>,
+    args: String,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = std::format!("{value:?}");
+        if !self.args.is_empty() {
+            self.args.push(',');
+        }
+        escape(&mut self.args, field.name());
+        self.args.push(':');
+        escape(&mut self.args, &value);
+        match field.name() {
+            "schedule" => self.schedule = Some(value),
+            "plugin" => self.plugin = Some(value),
+            "system" => self.system = Some(value),
+            "message" => self.message = Some(value),
+            _ => {}
+        }
+    }
+}
+
+/// Trace-event thread id of the current thread, and whether it is the
+/// first time this thread asks for it.
+fn thread_id() -> (u64, bool) {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    std::thread_local! {
+        static TID: Cell<u64> = const { Cell::new(0) };
+    }
+    TID.with(|tid| match tid.get() {
+        0 => {
+            let id = NEXT.fetch_add(1, Ordering::Relaxed);
+            tid.set(id);
+            (id, true)
+        }
+        id => (id, false),
+    })
+}
+
+/// Appends `s` as a JSON string literal.
+fn escape(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,353 @@
+//! Allocation-free profiler for targets `tracing` can't run on
+//! (`profile-lite` feature).
+//!
+//! Every system call is timed with a user-supplied cycle counter (such as
+//! the Cortex-M `DWT::cycle_count`), and folded into a fixed-size table of
+//! min/max/mean statistics per (schedule, plugin, system):
+//!
+//! ```rust
+//! use typed_ecs::profile_lite::{self, Profiler, Slot};
+//!
+//! fn cycles() -> u32 {
+//!     // e.g. `cortex_m::peripheral::DWT::cycle_count()`
+//!     0
+//! }
+//!
+//! static SLOTS: [Slot; 64] = [const { Slot::new() }; 64];
+//! static PROFILER: Profiler = Profiler::new(cycles, &SLOTS);
+//!
+//! profile_lite::install(&PROFILER);
+//! // ... run the App, then, e.g. from a plugin's `on_exit`:
+//! let mut dump = String::new();
+//! PROFILER.dump(&mut dump).unwrap();
+//! ```
+//!
+//! Until a profiler is installed, the hooks don't read the clock at all.
+//!
+//! Nothing blocks, so systems can be timed from any context, interrupt
+//! handlers included: a sample whose slot is busy in another context (the
+//! code it interrupted, or another core) is dropped, and counted by
+//! `Profiler::dropped`. Likewise, reading the table skips the rows busy at
+//! that moment.
+
+use core::{cell::UnsafeCell, fmt, ptr};
+
+use portable_atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicU32, Ordering};
+
+use crate::{guard::NoopGuard, instrumentation::Instrumentation};
+
+/// Reads a free-running cycle counter. Wrapping around is fine, as long
+/// as a single system call takes less than a full period.
+pub type Clock = fn() -> u32;
+
+const FREE: u8 = 0;
+const CLAIMING: u8 = 1;
+const KEYED: u8 = 2;
+
+/// Timing statistics of a system, in clock cycles.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SystemStats {
+    pub count: u32,
+    pub min: u32,
+    pub max: u32,
+    pub total: u64,
+}
+
+impl SystemStats {
+    const EMPTY: Self = Self {
+        count: 0,
+        min: u32::MAX,
+        max: 0,
+        total: 0,
+    };
+
+    #[inline(always)]
+    fn record(&mut self, cycles: u32) {
+        self.count = self.count.saturating_add(1);
+        self.min = self.min.min(cycles);
+        self.max = self.max.max(cycles);
+        self.total = self.total.saturating_add(cycles as u64);
+    }
+
+    pub fn mean(&self) -> u32 {
+        match self.count {
+            0 => 0,
+            count => (self.total / count as u64) as u32,
+        }
+    }
+}
+
+/// Row of the statistics table, as read back with `Profiler::entries`.
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub schedule: &'static str,
+    pub plugin: &'static str,
+    pub system: &'static str,
+    pub stats: SystemStats,
+}
+
+type Key = (&'static str, &'static str, &'static str);
+
+/// Storage for the statistics of one system. A profiler needs one slot
+/// per (schedule, plugin, system) it sees, that is 18 per plugin, as the
+/// default (empty) systems are timed too. The samples of the systems that
+/// don't fit are counted by `Profiler::dropped`.
+pub struct Slot {
+    state: AtomicU8,
+    locked: AtomicBool,
+    key: UnsafeCell<Key>,
+    stats: UnsafeCell<SystemStats>,
+}
+
+// SAFETY: `key` is only written while claiming a FREE slot, and read once
+// KEYED; `stats` is only accessed by whoever set `locked`.
+unsafe impl Sync for Slot {}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Slot {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(FREE),
+            locked: AtomicBool::new(false),
+            key: UnsafeCell::new(("", "", "")),
+            stats: UnsafeCell::new(SystemStats::EMPTY),
+        }
+    }
+
+    /// The key of the slot, once it's been claimed (not while it is being
+    /// claimed).
+    fn key(&self) -> Option<Key> {
+        // SAFETY: KEYED keys are never written again.
+        (self.state.load(Ordering::Acquire) == KEYED).then(|| unsafe { *self.key.get() })
+    }
+
+    /// Runs `f` on the statistics, unless another context is at it.
+    fn try_with_stats<R>(&self, f: impl FnOnce(&mut SystemStats) -> R) -> Option<R> {
+        if self.locked.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: `locked` was unset: the stats are ours until we unset it.
+        let result = f(unsafe { &mut *self.stats.get() });
+        self.locked.store(false, Ordering::Release);
+        Some(result)
+    }
+}
+
+/// Cycle-count statistics table, fed by the collection's hooks once
+/// `install`ed.
+pub struct Profiler {
+    clock: Clock,
+    slots: &'static [Slot],
+    dropped: AtomicU32,
+}
+
+impl Profiler {
+    pub const fn new(clock: Clock, slots: &'static [Slot]) -> Self {
+        Self {
+            clock,
+            slots,
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Number of samples that have been discarded, the table being full or
+    /// their slot busy.
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Statistics of every system seen so far, in no particular order.
+    /// The ones being recorded in another context are skipped.
+    pub fn entries(&self) -> impl Iterator<Item = Entry> + '_ {
+        self.slots.iter().filter_map(|slot| {
+            let (schedule, plugin, system) = slot.key()?;
+            Some(Entry {
+                schedule,
+                plugin,
+                system,
+                stats: slot.try_with_stats(|stats| *stats)?,
+            })
+        })
+    }
+
+    /// Statistics of a single system.
+    pub fn get(&self, schedule: &str, plugin: &str, system: &str) -> Option<SystemStats> {
+        self.entries()
+            .find(|e| e.schedule == schedule && e.plugin == plugin && e.system == system)
+            .map(|e| e.stats)
+    }
+
+    /// Clears the statistics, keeping the table layout. The ones being
+    /// recorded in another context are left as is.
+    pub fn reset(&self) {
+        for slot in self.slots {
+            if slot.key().is_some() {
+                slot.try_with_stats(|stats| *stats = SystemStats::EMPTY);
+            }
+        }
+        self.dropped.store(0, Ordering::Relaxed);
+    }
+
+    /// Writes the table as text, one system per line.
+    pub fn dump(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(
+            out,
+            "{:<24} {:<24} {:<24} {:>8} {:>10} {:>10} {:>10}",
+            "schedule", "plugin", "system", "count", "min", "mean", "max"
+        )?;
+        for Entry {
+            schedule,
+            plugin,
+            system,
+            stats,
+        } in self.entries()
+        {
+            if stats.count == 0 {
+                continue;
+            }
+            writeln!(
+                out,
+                "{schedule:<24} {plugin:<24} {system:<24} {:>8} {:>10} {:>10} {:>10}",
+                stats.count,
+                stats.min,
+                stats.mean(),
+                stats.max
+            )?;
+        }
+        match self.dropped() {
+            0 => Ok(()),
+            dropped => writeln!(out, "{dropped} samples dropped: table full or slots busy"),
+        }
+    }
+
+    fn record(&self, key: Key, cycles: u32) {
+        // Open addressing: slots are never freed, so a lookup stops at the
+        // first free slot of its probe sequence.
+        let len = self.slots.len();
+        let start = hash(key) % len.max(1);
+        for i in 0..len {
+            let slot = &self.slots[(start + i) % len];
+            let mut state = slot.state.load(Ordering::Acquire);
+            if state == FREE {
+                match slot.state.compare_exchange(
+                    FREE,
+                    CLAIMING,
+                    Ordering::Acquire,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: we own the slot until it's KEYED.
+                        unsafe { *slot.key.get() = key };
+                        slot.state.store(KEYED, Ordering::Release);
+                        return self.record_into(slot, cycles);
+                    }
+                    Err(current) => state = current,
+                }
+            }
+            match state {
+                // SAFETY: KEYED keys are never written again.
+                KEYED if key_eq(unsafe { *slot.key.get() }, key) => {
+                    return self.record_into(slot, cycles);
+                }
+                // Another key: keep probing.
+                KEYED => {}
+                // Being claimed, maybe for this key, by the context this one
+                // interrupted: probing further could key a second slot.
+                _ => break,
+            }
+        }
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_into(&self, slot: &Slot, cycles: u32) {
+        if slot.try_with_stats(|stats| stats.record(cycles)).is_none() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// FNV-1a over the three labels.
+fn hash((schedule, plugin, system): Key) -> usize {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in schedule.bytes().chain(plugin.bytes()).chain(system.bytes()) {
+        hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+    }
+    hash as usize
+}
+
+#[inline(always)]
+fn key_eq(a: Key, b: Key) -> bool {
+    (ptr::eq(a.0, b.0) || a.0 == b.0)
+        && (ptr::eq(a.1, b.1) || a.1 == b.1)
+        && (ptr::eq(a.2, b.2) || a.2 == b.2)
+}
+
+static INSTALLED: AtomicPtr<Profiler> = AtomicPtr::new(ptr::null_mut());
+
+/// Makes `profiler` the one the hooks record into, from now on.
+pub fn install(profiler: &'static Profiler) {
+    INSTALLED.store(ptr::from_ref(profiler).cast_mut(), Ordering::Release);
+}
+
+/// The installed profiler, for plugins to read the statistics at runtime.
+pub fn installed() -> Option<&'static Profiler> {
+    // SAFETY: only ever set from a `&'static Profiler`.
+    unsafe { INSTALLED.load(Ordering::Acquire).as_ref() }
+}
+
+/// Instrumentation recording into the installed profiler. The default
+/// instrumentation with the `profile-lite` feature.
+#[derive(Default, Clone, Copy)]
+pub struct ProfileLite;
+
+impl Instrumentation for ProfileLite {
+    #[inline(always)]
+    fn on_schedule_start(&self, _schedule: &'static str) -> impl Drop {
+        NoopGuard
+    }
+
+    #[inline(always)]
+    fn on_system_start(
+        &self,
+        schedule: &'static str,
+        plugin: &'static str,
+        system: &'static str,
+    ) -> impl Drop {
+        SystemGuard::start(schedule, plugin, system)
+    }
+}
+
+/// Times a system call until dropped. Returned by the
+/// `on_system_start` hook of `ProfileLite`.
+pub struct SystemGuard {
+    profiler: Option<&'static Profiler>,
+    key: Key,
+    start: u32,
+}
+
+impl SystemGuard {
+    #[inline(always)]
+    pub fn start(schedule: &'static str, plugin: &'static str, system: &'static str) -> Self {
+        let profiler = installed();
+        Self {
+            profiler,
+            key: (schedule, plugin, system),
+            start: profiler.map_or(0, |profiler| (profiler.clock)()),
+        }
+    }
+}
+
+impl Drop for SystemGuard {
+    #[inline(always)]
+    fn drop(&mut self) {
+        if let Some(profiler) = self.profiler {
+            let cycles = (profiler.clock)().wrapping_sub(self.start);
+            profiler.record(self.key, cycles);
+        }
+    }
+}
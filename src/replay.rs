@@ -0,0 +1,684 @@
+//! Recording and deterministic replay of a run (`replay` feature).
+//!
+//! An app run by a [`RecordExecutor`] logs the state of its shared data
+//! after every apply schedule, as encoded by `SharedData::record`, to a
+//! compact binary log. Encoding only the fields written by the input
+//! plugins (sensors, network, user input, ...) is enough: an app run by a
+//! [`ReplayExecutor`], whose collection doesn't contain them, then gets
+//! the recorded fields written back after each apply schedule, so the
+//! other plugins see exactly what they saw during the recorded run.
+//!
+//! ```rust
+//! use typed_ecs::{
+//!     app::App,
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     replay::{Decoder, Encoder, RecordExecutor, ReplayError, ReplayExecutor},
+//!     shared_data::SharedData,
+//!     should_exit::ShouldExit,
+//! };
+//!
+//! #[derive(Default)]
+//! struct World {
+//!     // Written by InputPlugin: recorded.
+//!     input: i64,
+//!     // Derived from the input by SumPlugin.
+//!     sum: i64,
+//! }
+//!
+//! impl SharedData for World {
+//!     fn build() -> Self {
+//!         Self::default()
+//!     }
+//!
+//!     fn record(&self, out: &mut Encoder) {
+//!         out.put_i64(self.input);
+//!     }
+//!
+//!     fn replay(&mut self, input: &mut Decoder<'_>) -> Result<(), ReplayError> {
+//!         self.input = input.get_i64()?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! struct InputPlugin(i64);
+//!
+//! impl Plugin<World> for InputPlugin {
+//!     fn build() -> Self {
+//!         Self(0)
+//!     }
+//!
+//!     fn apply_pre_update(&mut self, sd: &mut World) {
+//!         self.0 += 1;
+//!         sd.input = self.0 * 7 % 5;
+//!     }
+//!
+//!     fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &World) {
+//!         if self.0 == 10 {
+//!             should_exit.request_exit();
+//!         }
+//!     }
+//! }
+//!
+//! struct SumPlugin;
+//!
+//! impl Plugin<World> for SumPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//!
+//!     fn apply_update(&mut self, sd: &mut World) {
+//!         sd.sum += sd.input;
+//!     }
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let mut log = Vec::new();
+//! let recorded_sum = {
+//!     generate_collection!(InputPlugin, SumPlugin);
+//!     let executor = RecordExecutor::new(&mut log);
+//!     let mut app = App::new_with_executor(build_generated_collection::<World>(), executor);
+//!     app.run().await;
+//!     assert!(app.executor_mut().take_error().is_none());
+//!     app.shared_data.sum
+//! };
+//!
+//! generate_collection!(SumPlugin);
+//! let executor = ReplayExecutor::new(&log[..]).unwrap();
+//! let mut app = App::new_with_executor(build_generated_collection::<World>(), executor);
+//! app.run().await;
+//! assert!(app.executor_mut().take_error().is_none());
+//! assert_eq!(app.executor().frames(), 10);
+//! assert_eq!(app.shared_data.sum, recorded_sum);
+//! # }
+//! ```
+//!
+//! Both executors call the `before_frame`/`after_frame` and
+//! `run_exit_hooks` of the executor they wrap (`DefaultExecutor` by
+//! default) around the frames they run: a recorded or replayed run
+//! behaves like a run of that executor.
+//!
+//! The log starts with a versioned header. Each apply schedule then adds a
+//! one-byte record when the encoded state didn't change since the previous
+//! apply schedule, or the length-prefixed encoded state otherwise.
+
+use core::{fmt, ops::ControlFlow, pin::pin};
+use std::{
+    io::{self, Read, Write},
+    vec::Vec,
+};
+
+use crate::{
+    app::App,
+    executor::{DefaultExecutor, ExecutorTrait},
+    instrumentation::Instrumentation,
+    plugin_collection::PluginCollection,
+    shared_data::SharedData,
+    should_exit::ExitReason,
+};
+
+/// Apply schedules, in the order they run. Their index is their id in the
+/// log.
+pub const APPLY_SCHEDULES: [&str; 8] = [
+    "ApplyStartup",
+    "ApplyAsyncStartup",
+    "ApplyPreUpdate",
+    "ApplyAsyncPreUpdate",
+    "ApplyUpdate",
+    "ApplyPostUpdate",
+    "ApplyAsyncPostUpdate",
+    "ApplyAsyncUpdate",
+];
+
+const MAGIC: &[u8; 7] = b"TECSRPL";
+const VERSION: u8 = 1;
+
+// Record tags, besides the index of an apply schedule.
+const UNCHANGED: u8 = 0x80;
+const FRAME: u8 = 0xF0;
+const EXIT: u8 = 0xF1;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// Not a log, or one written by an incompatible version.
+    BadHeader,
+    /// The log ends in the middle of a record.
+    Truncated,
+    /// The log doesn't match the schedules the app runs.
+    Desync {
+        frame: u64,
+        schedule: &'static str,
+    },
+    /// The replayed app requested exit before the end of the log.
+    Diverged {
+        frame: u64,
+    },
+    /// A payload `SharedData::replay` can't decode.
+    Invalid,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "replay log I/O: {error}"),
+            ReplayError::BadHeader => f.write_str("not a replay log, or an incompatible version"),
+            ReplayError::Truncated => f.write_str("truncated replay log"),
+            ReplayError::Desync { frame, schedule } => {
+                write!(f, "replay log out of sync at frame {frame}, {schedule}")
+            }
+            ReplayError::Diverged { frame } => {
+                write!(
+                    f,
+                    "replayed app exited at frame {frame}, before the end of the log"
+                )
+            }
+            ReplayError::Invalid => f.write_str("invalid payload in replay log"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+/// Encoding of the state of the shared data (see `SharedData::record`).
+/// Integers are variable-length encoded, so small values take a single
+/// byte.
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn put_bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn put_u64(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    pub fn put_i64(&mut self, value: i64) {
+        self.put_u64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn put_f32(&mut self, value: f32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_f64(&mut self, value: f64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Length-prefixed bytes.
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        self.put_u64(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// Reads back what an [`Encoder`] wrote, in the same order.
+pub struct Decoder<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self { input }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, ReplayError> {
+        let (&byte, rest) = self.input.split_first().ok_or(ReplayError::Truncated)?;
+        self.input = rest;
+        Ok(byte)
+    }
+
+    pub fn get_bool(&mut self) -> Result<bool, ReplayError> {
+        match self.get_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ReplayError::Invalid),
+        }
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, ReplayError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.get_u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ReplayError::Invalid)
+    }
+
+    pub fn get_i64(&mut self) -> Result<i64, ReplayError> {
+        let value = self.get_u64()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub fn get_f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(*self.take_array()?))
+    }
+
+    pub fn get_f64(&mut self) -> Result<f64, ReplayError> {
+        Ok(f64::from_le_bytes(*self.take_array()?))
+    }
+
+    /// Bytes written by `Encoder::put_bytes`.
+    pub fn get_bytes(&mut self) -> Result<&'a [u8], ReplayError> {
+        let len = usize::try_from(self.get_u64()?).map_err(|_| ReplayError::Invalid)?;
+        self.take(len)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        if self.input.len() < len {
+            return Err(ReplayError::Truncated);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<&'a [u8; N], ReplayError> {
+        Ok(self.take(N)?.try_into().expect("taken N bytes"))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+}
+
+fn schedule_id(schedule: &'static str) -> u8 {
+    APPLY_SCHEDULES
+        .iter()
+        .position(|s| *s == schedule)
+        .expect("an apply schedule") as u8
+}
+
+struct Sink<W: Write> {
+    out: W,
+    // First error, after which nothing is written.
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Sink<W> {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_none()
+            && let Err(error) = self.out.write_all(bytes)
+        {
+            self.error = Some(error);
+        }
+    }
+
+    fn result(&mut self) -> Result<(), ReplayError> {
+        match self.error.take() {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+struct Recorder<W: Write> {
+    sink: Sink<W>,
+    encoder: Encoder,
+    // State written by the previous record.
+    last: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    fn record<SD: SharedData>(&mut self, schedule: &'static str, sd: &SD) {
+        let id = schedule_id(schedule);
+        self.encoder.buf.clear();
+        sd.record(&mut self.encoder);
+
+        let last = &mut self.last;
+        if *last == self.encoder.buf {
+            self.sink.write(&[id | UNCHANGED]);
+            return;
+        }
+        core::mem::swap(last, &mut self.encoder.buf);
+
+        self.encoder.buf.clear();
+        self.encoder.put_u8(id);
+        self.encoder.put_u64(last.len() as u64);
+        self.sink.write(&self.encoder.buf);
+        self.sink.write(last);
+    }
+}
+
+struct Replayer<'a> {
+    log: Decoder<'a>,
+    // State read by the previous record, restored by the "unchanged"
+    // ones.
+    last: &'a [u8],
+    frame: u64,
+    error: Option<ReplayError>,
+}
+
+impl Replayer<'_> {
+    fn replay<SD: SharedData>(&mut self, schedule: &'static str, sd: &mut SD) -> ControlFlow<()> {
+        if self.error.is_some() {
+            return ControlFlow::Break(());
+        }
+        if let Err(error) = self.restore(schedule, sd) {
+            self.error = Some(error);
+            return ControlFlow::Break(());
+        }
+        // The recorded run exited in this frame.
+        match self.log.peek() {
+            Some(EXIT) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn restore<SD: SharedData>(
+        &mut self,
+        schedule: &'static str,
+        sd: &mut SD,
+    ) -> Result<(), ReplayError> {
+        let id = schedule_id(schedule);
+        let desync = ReplayError::Desync {
+            frame: self.frame,
+            schedule,
+        };
+        let tag = self.log.get_u8()?;
+        let state = if tag == id | UNCHANGED {
+            self.last
+        } else if tag == id {
+            let len = usize::try_from(self.log.get_u64()?).map_err(|_| ReplayError::Invalid)?;
+            self.last = self.log.take(len)?;
+            self.last
+        } else {
+            return Err(desync);
+        };
+
+        // A short payload is a corrupted one, not a log cut short.
+        let mut state = Decoder::new(state);
+        sd.replay(&mut state).map_err(|error| match error {
+            ReplayError::Truncated => ReplayError::Invalid,
+            error => error,
+        })?;
+        if !state.is_empty() {
+            return Err(ReplayError::Invalid);
+        }
+        Ok(())
+    }
+}
+
+/// Runs an app like `E` does, while logging its shared data to a writer
+/// after every apply schedule. Give it a buffered writer, such as a
+/// `BufWriter<File>` or a `Vec<u8>`.
+///
+/// The hooks of `E` are called, but not its `run`: the frames follow each
+/// other as with `DefaultExecutor`. A failure to write the log stops the
+/// run, with `ExitReason::Interrupted` (see
+/// [`RecordExecutor::take_error`]).
+pub struct RecordExecutor<W: Write, E: ExecutorTrait = DefaultExecutor> {
+    inner: E,
+    recorder: Recorder<W>,
+}
+
+impl<W: Write> RecordExecutor<W> {
+    /// Passed to `App::new_with_executor`.
+    pub fn new(out: W) -> Self {
+        Self::with_executor(out, DefaultExecutor)
+    }
+}
+
+impl<W: Write, E: ExecutorTrait> RecordExecutor<W, E> {
+    /// Records a run whose hooks are the ones of `inner`.
+    pub fn with_executor(out: W, inner: E) -> Self {
+        Self {
+            inner,
+            recorder: Recorder {
+                sink: Sink { out, error: None },
+                encoder: Encoder::default(),
+                last: Vec::new(),
+            },
+        }
+    }
+
+    /// The executor whose hooks are called.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Takes the error that stopped the last run, if writing the log
+    /// failed.
+    pub fn take_error(&mut self) -> Option<ReplayError> {
+        self.recorder.sink.result().err()
+    }
+}
+
+impl<W: Write, E: ExecutorTrait> ExecutorTrait for RecordExecutor<W, E> {
+    async fn run<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+    ) -> ExitReason {
+        let recorder = &mut self.recorder;
+        recorder.last.clear();
+        recorder.sink.write(MAGIC);
+        recorder.sink.write(&[VERSION]);
+
+        app.run_startup_with(|schedule, sd| recorder.record(schedule, sd))
+            .await;
+        if recorder.sink.error.is_some() {
+            return ExitReason::Interrupted;
+        }
+
+        let mut tasks = pin!(app.background_tasks());
+        loop {
+            self.inner.before_frame(app);
+            recorder.sink.write(&[FRAME]);
+            let should_exit = app
+                .run_frame_with(tasks.as_mut(), |schedule, sd| {
+                    recorder.record(schedule, sd);
+                    ControlFlow::Continue(())
+                })
+                .await;
+            self.inner.after_frame(app, should_exit);
+
+            if should_exit {
+                recorder.sink.write(&[EXIT]);
+                if let Err(error) = recorder.sink.out.flush() {
+                    recorder.sink.error.get_or_insert(error);
+                }
+            }
+            if recorder.sink.error.is_some() {
+                return ExitReason::Interrupted;
+            }
+            if should_exit {
+                return app.exit_reason();
+            }
+        }
+    }
+
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+        reason: &ExitReason,
+    ) {
+        self.inner.run_exit_hooks(app, reason);
+    }
+}
+
+/// Runs an app against a log written by a [`RecordExecutor`], restoring
+/// the recorded state of the shared data after every apply schedule,
+/// until the end of the log.
+///
+/// The collection of the app is usually the recorded one, without its
+/// input plugins. A log cut short (e.g. the recorded run crashed) is
+/// replayed up to where it ends. As for [`RecordExecutor`], the hooks of
+/// `E` are called, but not its `run`. A log that doesn't match the app
+/// stops the run, with `ExitReason::Interrupted` (see
+/// [`ReplayExecutor::take_error`]).
+pub struct ReplayExecutor<E: ExecutorTrait = DefaultExecutor> {
+    inner: E,
+    log: Vec<u8>,
+    frames: u64,
+    error: Option<ReplayError>,
+}
+
+impl ReplayExecutor {
+    /// Reads the whole log from `input`, and checks its header.
+    pub fn new(input: impl Read) -> Result<Self, ReplayError> {
+        Self::with_executor(input, DefaultExecutor)
+    }
+}
+
+impl<E: ExecutorTrait> ReplayExecutor<E> {
+    /// Replays a run whose hooks are the ones of `inner`.
+    pub fn with_executor(mut input: impl Read, inner: E) -> Result<Self, ReplayError> {
+        let mut log = Vec::new();
+        input.read_to_end(&mut log)?;
+        if log.get(..MAGIC.len()) != Some(MAGIC) || log.get(MAGIC.len()) != Some(&VERSION) {
+            return Err(ReplayError::BadHeader);
+        }
+        Ok(Self {
+            inner,
+            log,
+            frames: 0,
+            error: None,
+        })
+    }
+
+    /// The executor whose hooks are called.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Number of frames the last run replayed.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Takes the error that stopped the last run, if the log didn't match
+    /// the app.
+    pub fn take_error(&mut self) -> Option<ReplayError> {
+        self.error.take()
+    }
+}
+
+impl<E: ExecutorTrait> ExecutorTrait for ReplayExecutor<E> {
+    async fn run<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+    ) -> ExitReason {
+        let mut replayer = Replayer {
+            log: Decoder::new(&self.log[MAGIC.len() + 1..]),
+            last: &[],
+            frame: 0,
+            error: None,
+        };
+        let inner = &mut self.inner;
+
+        let result = async {
+            app.run_startup_with(|schedule, sd| {
+                let _ = replayer.replay(schedule, sd);
+            })
+            .await;
+            match replayer.error.take() {
+                Some(ReplayError::Truncated) => return Ok(()),
+                Some(error) => return Err(error),
+                None => {}
+            }
+
+            let mut tasks = pin!(app.background_tasks());
+            loop {
+                match replayer.log.peek() {
+                    None => return Ok(()),
+                    Some(FRAME) => _ = replayer.log.get_u8(),
+                    Some(_) => {
+                        return Err(ReplayError::Desync {
+                            frame: replayer.frame,
+                            schedule: "PreUpdate",
+                        });
+                    }
+                }
+                inner.before_frame(app);
+                let should_exit = app
+                    .run_frame_with(tasks.as_mut(), |schedule, sd| replayer.replay(schedule, sd))
+                    .await;
+                inner.after_frame(app, should_exit);
+
+                match replayer.error.take() {
+                    // A frame cut short by the end of the log isn't counted.
+                    Some(ReplayError::Truncated) => return Ok(()),
+                    Some(error) => return Err(error),
+                    None => {}
+                }
+                replayer.frame += 1;
+                if replayer.log.peek() == Some(EXIT) {
+                    return Ok(());
+                }
+                if should_exit {
+                    return Err(ReplayError::Diverged {
+                        frame: replayer.frame,
+                    });
+                }
+            }
+        }
+        .await;
+
+        self.frames = replayer.frame;
+        match result {
+            Ok(()) => app.exit_reason(),
+            Err(error) => {
+                self.error = Some(error);
+                ExitReason::Interrupted
+            }
+        }
+    }
+
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+        reason: &ExitReason,
+    ) {
+        self.inner.run_exit_hooks(app, reason);
+    }
+}
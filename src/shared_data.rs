@@ -7,7 +7,11 @@
 /// methods.
 ///
 /// Example:
-/// ```rust
+/// ```rust,compile_fail
+/// use typed_ecs::{
+///     app::App, macros::generate_collection, plugin::Plugin, shared_data::SharedData,
+/// };
+///
 /// struct Ping {
 ///     pinged: bool
 /// }
@@ -46,15 +50,34 @@
 /// }
 ///
 /// fn main() {
-///     let app = App::new_with_SharedData::<NonValidSharedData>();
+///     generate_collection!(PingerPlugin, WaitingPlugin);
 ///     // Compile error! Plugins just can't be added into the plugin
 ///     // typed system because type requirements aren't met!
-///     let app = app.add_plugins::<(PingerPlugin, WaitingPlugin)>();
-///     app.build().run();
+///     let collection: GeneratedPluginCollection<NonValidSharedData> =
+///         build_generated_collection();
+///     let _app = App::new(collection);
 /// }
 /// ```
 pub trait SharedData: Sync {
     fn build() -> Self;
+
+    // REPLAY (`replay` feature, see `replay::RecordExecutor`)
+
+    /// Encodes the state to restore on replay, usually the fields the
+    /// input plugins write. Nothing is recorded by default.
+    #[cfg(feature = "replay")]
+    #[inline(always)]
+    fn record(&self, _out: &mut crate::replay::Encoder) {}
+
+    /// Restores the state encoded by `SharedData::record`.
+    #[cfg(feature = "replay")]
+    #[inline(always)]
+    fn replay(
+        &mut self,
+        _input: &mut crate::replay::Decoder<'_>,
+    ) -> Result<(), crate::replay::ReplayError> {
+        Ok(())
+    }
 }
 
 pub struct PhantomSharedData;
@@ -11,8 +11,28 @@ impl ShouldExit for bool {
     fn request_exit(&mut self) {
         *self = true;
     }
-    
+
     fn is_true(&self) -> bool {
         *self
     }
 }
+
+/// Why `App::run` returned, handed to `ExecutorTrait::run_exit_hooks`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExitReason {
+    /// A plugin requested exit in the ExitCheck schedule.
+    Requested,
+    /// The run stopped before any exit request: a system panicked, the
+    /// `App::run` future was dropped before its completion, or the
+    /// executor gave up (e.g. `replay::RecordExecutor` failed to write its
+    /// log).
+    Interrupted,
+    /// A plugin panicked, and its `OnPanic::Exit` policy requested exit
+    /// (`panic-isolation` feature).
+    #[cfg(feature = "panic-isolation")]
+    PluginPanicked(crate::panic_isolation::PanicReport),
+    /// A budget has been exceeded, and the collection's `Watchdog`
+    /// requested exit (`budget` feature).
+    #[cfg(feature = "budget")]
+    BudgetExceeded(crate::budget::BudgetViolation),
+}
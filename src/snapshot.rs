@@ -0,0 +1,306 @@
+//! Snapshots of an app, for save games and crash dumps (`serde` feature).
+//!
+//! `App::save_snapshot` encodes the shared data, then the state each
+//! plugin saves in `Plugin::save_state`, into a caller-provided buffer,
+//! with [`postcard`](https://docs.rs/postcard). `App::load_snapshot`
+//! restores them on an app of the same collection, e.g. a new one before
+//! its startup, or at a frame boundary.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use typed_ecs::{
+//!     app::App,
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     shared_data::SharedData,
+//!     snapshot::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter},
+//! };
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Game {
+//!     level: u8,
+//!     score: u32,
+//! }
+//!
+//! impl SharedData for Game {
+//!     fn build() -> Self {
+//!         Self::default()
+//!     }
+//! }
+//!
+//! impl Snapshot for Game {
+//!     const SCHEMA: &'static str = "game";
+//!     const VERSION: u32 = 1;
+//! }
+//!
+//! struct ComboPlugin {
+//!     combo: u16,
+//! }
+//!
+//! impl Plugin<Game> for ComboPlugin {
+//!     fn build() -> Self {
+//!         Self { combo: 0 }
+//!     }
+//!
+//!     fn save_state(&self, out: &mut SnapshotWriter<'_>) -> Result<(), SnapshotError> {
+//!         out.put(&self.combo)
+//!     }
+//!
+//!     fn load_state(
+//!         input: &mut SnapshotReader<'_>,
+//!     ) -> Result<impl FnOnce(&mut Self) + use<>, SnapshotError> {
+//!         let combo = input.get()?;
+//!         Ok(move |plugin: &mut Self| plugin.combo = combo)
+//!     }
+//! }
+//!
+//! generate_collection!(ComboPlugin);
+//!
+//! let mut app = App::new(build_generated_collection::<Game>());
+//! app.shared_data.score = 1200;
+//! app.plugin_collection.comboplugin.combo = 7;
+//!
+//! let mut buf = [0; 64];
+//! let bytes = app.save_snapshot(&mut buf).unwrap();
+//!
+//! let mut restored = App::new(build_generated_collection::<Game>());
+//! restored.load_snapshot(bytes).unwrap();
+//! assert_eq!(restored.shared_data.score, 1200);
+//! assert_eq!(restored.plugin_collection.comboplugin.combo, 7);
+//! ```
+//!
+//! A snapshot starts with a header holding `Snapshot::VERSION` and a
+//! fingerprint of `Snapshot::SCHEMA` and of the plugins of the collection
+//! (their names, in order): a snapshot of another version, or of another
+//! app, is rejected before anything is decoded. Both only depend on the
+//! source, not on the compiler: a save stays loadable by the builds of
+//! another toolchain.
+//!
+//! The shared data and each plugin state follow in sections, prefixed
+//! with their length. Loading checks every length, then decodes every
+//! section, and only then restores the app: `Plugin::load_state` decodes,
+//! and returns what restores the plugin.
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::shared_data::SharedData;
+
+/// Shared data that can be saved to, and restored from, a snapshot.
+pub trait Snapshot: SharedData + Serialize + DeserializeOwned {
+    /// Identifies the shared data in snapshots, e.g. the name of the game:
+    /// the snapshots of another schema are rejected.
+    const SCHEMA: &'static str;
+
+    /// Version of the serialized layout: bump it whenever the layout of
+    /// the shared data (or of a plugin state) changes, for the snapshots of
+    /// the previous versions to be rejected.
+    const VERSION: u32;
+}
+
+const MAGIC: &[u8; 8] = b"TECSSNAP";
+const FORMAT: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SnapshotError {
+    /// The buffer is too small for the snapshot.
+    BufferFull,
+    /// Not a snapshot, or one of an incompatible format.
+    BadHeader,
+    /// A snapshot of another `Snapshot::VERSION`.
+    Version { expected: u32, found: u32 },
+    /// A snapshot of another `Snapshot::SCHEMA`, or of other plugins.
+    Layout,
+    /// The shared data, or a plugin state, can't be decoded.
+    Invalid,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BufferFull => f.write_str("snapshot buffer full"),
+            SnapshotError::BadHeader => f.write_str("not a snapshot, or an incompatible format"),
+            SnapshotError::Version { expected, found } => {
+                write!(f, "snapshot version {found}, expected {expected}")
+            }
+            SnapshotError::Layout => f.write_str("snapshot of another schema or plugins"),
+            SnapshotError::Invalid => f.write_str("invalid snapshot data"),
+        }
+    }
+}
+
+impl From<postcard::Error> for SnapshotError {
+    fn from(error: postcard::Error) -> Self {
+        match error {
+            postcard::Error::SerializeBufferFull => SnapshotError::BufferFull,
+            _ => SnapshotError::Invalid,
+        }
+    }
+}
+
+/// Serializes values one after another into a buffer.
+pub struct SnapshotWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SnapshotWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub fn put<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SnapshotError> {
+        let used = postcard::to_slice(value, &mut self.buf[self.len..])?.len();
+        self.len += used;
+        Ok(())
+    }
+
+    pub(crate) fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(SnapshotError::BufferFull)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Writes what `f` puts, prefixed with its length. Called once for
+    /// the shared data, and once per plugin.
+    #[doc(hidden)]
+    pub fn section(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), SnapshotError>,
+    ) -> Result<(), SnapshotError> {
+        let start = self.len;
+        self.put_bytes(&[0; 4])?;
+        f(self)?;
+        let len = (self.len - start - 4) as u32;
+        self.buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> &'a mut [u8] {
+        &mut self.buf[..self.len]
+    }
+}
+
+/// Deserializes the values a [`SnapshotWriter`] wrote, in the same order.
+pub struct SnapshotReader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self { input }
+    }
+
+    pub fn get<T: Deserialize<'a>>(&mut self) -> Result<T, SnapshotError> {
+        let (value, rest) = postcard::take_from_bytes(self.input)?;
+        self.input = rest;
+        Ok(value)
+    }
+
+    pub(crate) fn get_bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.input.len() < len {
+            return Err(SnapshotError::Invalid);
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    /// Hands a section written by `SnapshotWriter::section` to `f`, which
+    /// has to read it whole. Called once for the shared data, and once
+    /// per plugin.
+    #[doc(hidden)]
+    pub fn section<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<T, SnapshotError> {
+        let len = self.section_len()?;
+        let mut section = SnapshotReader::new(self.get_bytes(len)?);
+        let value = f(&mut section)?;
+        if !section.input.is_empty() {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(value)
+    }
+
+    /// Checks that exactly `count` sections follow, without decoding
+    /// them.
+    #[doc(hidden)]
+    pub fn check_sections(&self, count: usize) -> Result<(), SnapshotError> {
+        let mut input = SnapshotReader::new(self.input);
+        for _ in 0..count {
+            let len = input.section_len()?;
+            input.get_bytes(len)?;
+        }
+        if !input.input.is_empty() {
+            return Err(SnapshotError::Invalid);
+        }
+        Ok(())
+    }
+
+    fn section_len(&mut self) -> Result<usize, SnapshotError> {
+        let len = u32::from_le_bytes(self.get_bytes(4)?.try_into().expect("4 bytes"));
+        Ok(len as usize)
+    }
+}
+
+/// FNV-1a, as a `const fn` for the generated collection to fingerprint
+/// its plugins at compile time.
+#[doc(hidden)]
+pub const fn fingerprint(seed: u64, bytes: &[u8]) -> u64 {
+    let mut hash = seed;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+#[doc(hidden)]
+pub const FINGERPRINT_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn layout<SD: Snapshot>(plugins: u64) -> u64 {
+    fingerprint(plugins, SD::SCHEMA.as_bytes())
+}
+
+pub(crate) fn write_header<SD: Snapshot>(
+    out: &mut SnapshotWriter<'_>,
+    plugins: u64,
+) -> Result<(), SnapshotError> {
+    out.put_bytes(MAGIC)?;
+    out.put_bytes(&[FORMAT])?;
+    out.put_bytes(&SD::VERSION.to_le_bytes())?;
+    out.put_bytes(&layout::<SD>(plugins).to_le_bytes())
+}
+
+pub(crate) fn read_header<SD: Snapshot>(
+    input: &mut SnapshotReader<'_>,
+    plugins: u64,
+) -> Result<(), SnapshotError> {
+    if input.input.len() < HEADER_LEN
+        || input.get_bytes(MAGIC.len())? != MAGIC
+        || input.get_bytes(1)? != [FORMAT]
+    {
+        return Err(SnapshotError::BadHeader);
+    }
+    let found = u32::from_le_bytes(input.get_bytes(4)?.try_into().expect("4 bytes"));
+    if found != SD::VERSION {
+        return Err(SnapshotError::Version {
+            expected: SD::VERSION,
+            found,
+        });
+    }
+    let found = u64::from_le_bytes(input.get_bytes(8)?.try_into().expect("8 bytes"));
+    if found != layout::<SD>(plugins) {
+        return Err(SnapshotError::Layout);
+    }
+    Ok(())
+}
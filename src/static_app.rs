@@ -0,0 +1,195 @@
+//! An `App` in a `static`, for targets without `alloc`.
+//!
+//! `SharedData::build` and `Plugin::build` aren't `const`, so an app can't
+//! be the initializer of a `static`. It is built once at runtime instead,
+//! into a [`StaticCell`]: the cell is a `static` whose content starts
+//! uninitialized, so it takes no flash and lives in `.bss`, and handing out
+//! the `&'static mut` only once makes it safe.
+//!
+//! [`static_app!`](crate::static_app!) declares the cell and initializes it
+//! in one go:
+//!
+//! ```rust
+//! use typed_ecs::{
+//!     app::App,
+//!     macros::generate_collection,
+//!     plugin::Plugin,
+//!     shared_data::{PhantomSharedData, SharedData},
+//!     should_exit::ShouldExit,
+//!     static_app,
+//! };
+//!
+//! struct BlinkPlugin;
+//!
+//! impl<SD: SharedData> Plugin<SD> for BlinkPlugin {
+//!     fn build() -> Self {
+//!         Self
+//!     }
+//!
+//!     fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+//!         should_exit.request_exit();
+//!     }
+//! }
+//!
+//! generate_collection!(BlinkPlugin);
+//!
+//! type MyApp = App<PhantomSharedData, GeneratedPluginCollection<PhantomSharedData>>;
+//!
+//! let app: &'static mut MyApp = static_app!(MyApp = App::new(build_generated_collection()));
+//! assert_eq!(app.collection_info().plugins.len(), 1);
+//!
+//! // Built in place: the collection is never on the stack, one plugin at a
+//! // time is.
+//! let app: &'static mut MyApp =
+//!     static_app!(MyApp => |slot| App::new_in(slot, build_generated_collection_in));
+//! assert_eq!(app.collection_info().plugins.len(), 1);
+//! ```
+//!
+//! `App::new` builds the app on the stack, then moves it into the cell: a
+//! stack of the size of the app is needed once. `App::new_in`, with the
+//! `build_generated_collection_in` function `generate_collection!` defines
+//! along `build_generated_collection`, writes it into the cell instead.
+//!
+//! The app is then driven from `main` (e.g. `app.run()` on an embassy
+//! executor), or moved into the interrupt handler that polls its frames.
+//! Being in a `static`, it is never dropped: `App::run` runs the
+//! `on_exit` systems itself, and a custom driver calls `App::run_shutdown`.
+
+use core::{cell::UnsafeCell, mem::MaybeUninit, ptr};
+
+use portable_atomic::{AtomicU8, Ordering};
+
+const EMPTY: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+
+/// A `static` holding a value initialized once, at runtime.
+///
+/// The cell is claimed before its value is built: if building it panics,
+/// the cell stays claimed without a value, for good. It can't be
+/// initialized again, and [`StaticCell::is_initialized`] stays false.
+pub struct StaticCell<T> {
+    /// `EMPTY`, then `INITIALIZING` while the value is built, then `READY`.
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// The value is only reachable through the `&'static mut` handed out by
+// `init`, once.
+unsafe impl<T: Send> Sync for StaticCell<T> {}
+
+impl<T> StaticCell<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Moves `value` into the cell.
+    ///
+    /// # Panics
+    ///
+    /// If the cell has already been initialized.
+    pub fn init(&'static self, value: T) -> &'static mut T {
+        self.init_with(|| value)
+    }
+
+    /// Initializes the cell with the value returned by `init`, called only
+    /// if the cell is still empty.
+    ///
+    /// # Panics
+    ///
+    /// If the cell has already been initialized.
+    pub fn init_with(&'static self, init: impl FnOnce() -> T) -> &'static mut T {
+        match self.try_init_with(init) {
+            Some(value) => value,
+            None => panic!("StaticCell already initialized"),
+        }
+    }
+
+    /// Like [`StaticCell::init_with`], returning `None` if the cell has
+    /// already been initialized.
+    pub fn try_init_with(&'static self, init: impl FnOnce() -> T) -> Option<&'static mut T> {
+        self.try_init_in_place(|slot| slot.write(init()))
+    }
+
+    /// Initializes the cell in place: `init` writes the value into the
+    /// slot it is given, and returns it, e.g. with `App::new_in`. Unlike
+    /// [`StaticCell::init_with`], the value never has to be on the stack
+    /// as a whole.
+    ///
+    /// # Panics
+    ///
+    /// If the cell has already been initialized, or if `init` returns
+    /// another value than the one of its slot.
+    pub fn init_in_place(
+        &'static self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> &'static mut T {
+        match self.try_init_in_place(init) {
+            Some(value) => value,
+            None => panic!("StaticCell already initialized"),
+        }
+    }
+
+    /// Like [`StaticCell::init_in_place`], returning `None` if the cell
+    /// has already been initialized.
+    // Unique: `state` only leaves `EMPTY` once.
+    #[allow(clippy::mut_from_ref)]
+    pub fn try_init_in_place(
+        &'static self,
+        init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    ) -> Option<&'static mut T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, INITIALIZING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        // SAFETY: the cell was empty, so nobody else has a reference to
+        // the value, and nobody will.
+        let slot = unsafe { &mut *self.value.get() };
+        let expected = slot.as_mut_ptr();
+        let value = init(slot);
+        assert!(
+            ptr::eq(value, expected),
+            "StaticCell initialized out of place"
+        );
+        self.state.store(READY, Ordering::Release);
+        Some(value)
+    }
+
+    /// Whether the value has been built and handed out: false while it is
+    /// built, and for good if building it panicked.
+    pub fn is_initialized(&self) -> bool {
+        self.state.load(Ordering::Acquire) == READY
+    }
+}
+
+impl<T> Default for StaticCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds an app (or any value) into a `static` of its own, returning it
+/// as `&'static mut`: `static_app!(Type = expr)` moves the value of `expr`
+/// into it, `static_app!(Type => closure)` has the closure write it in
+/// place (see [`StaticCell::init_in_place`]). See the
+/// [`static_app`](crate::static_app) module.
+///
+/// The `static` belongs to the call site: evaluating the same
+/// `static_app!` twice panics.
+#[macro_export]
+macro_rules! static_app {
+    ($ty:ty = $init:expr) => {{
+        static APP: $crate::static_app::StaticCell<$ty> = $crate::static_app::StaticCell::new();
+        APP.init_with(|| $init)
+    }};
+    ($ty:ty => $init:expr) => {{
+        static APP: $crate::static_app::StaticCell<$ty> = $crate::static_app::StaticCell::new();
+        APP.init_in_place($init)
+    }};
+}
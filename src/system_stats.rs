@@ -0,0 +1,223 @@
+//! Timing statistics maintained by the collection itself (`system-stats`
+//! feature): last, average and worst-case duration of every plugin system
+//! and of every schedule, readable while the app runs.
+//!
+//! Plugins receive them once per frame in `Plugin::on_system_stats`, and
+//! the executor can read them with `App::system_stats`.
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use std::time::Instant;
+
+use crate::introspection;
+
+/// Schedules of a collection, in their order in the table (the one of
+/// `introspection::SCHEDULES`).
+pub const SCHEDULES: [&str; SCHEDULE_NUM] = {
+    let mut names = [""; SCHEDULE_NUM];
+    let mut idx = 0;
+    while idx < SCHEDULE_NUM {
+        names[idx] = introspection::SCHEDULES[idx].name;
+        idx += 1;
+    }
+    names
+};
+
+/// Plugin system run by each of the `SCHEDULES`.
+pub const SYSTEMS: [&str; SCHEDULE_NUM] = {
+    let mut systems = [""; SCHEDULE_NUM];
+    let mut idx = 0;
+    while idx < SCHEDULE_NUM {
+        systems[idx] = introspection::SCHEDULES[idx].system;
+        idx += 1;
+    }
+    systems
+};
+
+pub const SCHEDULE_NUM: usize = introspection::SCHEDULES.len();
+
+/// Durations of a system or a schedule.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TimingStats {
+    /// Number of runs.
+    pub count: u64,
+    pub last: Duration,
+    pub average: Duration,
+    pub worst: Duration,
+}
+
+/// Lock-free accumulator behind a `TimingStats`. The fields are updated
+/// independently, so a read racing a write may mix two runs.
+#[derive(Default)]
+pub struct Timing {
+    count: AtomicU64,
+    last_ns: AtomicU64,
+    total_ns: AtomicU64,
+    worst_ns: AtomicU64,
+}
+
+impl Timing {
+    pub const fn new() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            last_ns: AtomicU64::new(0),
+            total_ns: AtomicU64::new(0),
+            worst_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Times the run until the guard is dropped.
+    #[inline(always)]
+    pub fn start(&self) -> TimingGuard<'_> {
+        TimingGuard {
+            timing: self,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn record(&self, duration: Duration) {
+        let ns = duration.as_nanos().min(u64::MAX as u128) as u64;
+        self.last_ns.store(ns, Ordering::Relaxed);
+        self.total_ns.fetch_add(ns, Ordering::Relaxed);
+        self.worst_ns.fetch_max(ns, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> TimingStats {
+        let count = self.count.load(Ordering::Relaxed);
+        let total_ns = self.total_ns.load(Ordering::Relaxed);
+        TimingStats {
+            count,
+            last: Duration::from_nanos(self.last_ns.load(Ordering::Relaxed)),
+            average: Duration::from_nanos(total_ns.checked_div(count).unwrap_or(0)),
+            worst: Duration::from_nanos(self.worst_ns.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.last_ns.store(0, Ordering::Relaxed);
+        self.total_ns.store(0, Ordering::Relaxed);
+        self.worst_ns.store(0, Ordering::Relaxed);
+    }
+}
+
+pub struct TimingGuard<'a> {
+    timing: &'a Timing,
+    start: Instant,
+}
+
+impl Drop for TimingGuard<'_> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.timing.record(self.start.elapsed());
+    }
+}
+
+/// Storage of the statistics of a collection of `N` plugins, maintained
+/// by the generated code.
+pub struct StatsTable<const N: usize> {
+    plugins: [&'static str; N],
+    systems: [[Timing; SCHEDULE_NUM]; N],
+    schedules: [Timing; SCHEDULE_NUM],
+}
+
+impl<const N: usize> StatsTable<N> {
+    pub const fn new(plugins: [&'static str; N]) -> Self {
+        Self {
+            plugins,
+            systems: [const { [const { Timing::new() }; SCHEDULE_NUM] }; N],
+            schedules: [const { Timing::new() }; SCHEDULE_NUM],
+        }
+    }
+
+    #[inline(always)]
+    pub fn system(&self, plugin: usize, schedule: usize) -> &Timing {
+        &self.systems[plugin][schedule]
+    }
+
+    #[inline(always)]
+    pub fn schedule(&self, schedule: usize) -> &Timing {
+        &self.schedules[schedule]
+    }
+
+    pub fn view(&self) -> SystemStats<'_> {
+        SystemStats {
+            plugins: &self.plugins,
+            systems: &self.systems,
+            schedules: &self.schedules,
+        }
+    }
+}
+
+/// Read access to the statistics of a collection.
+#[derive(Clone, Copy)]
+pub struct SystemStats<'a> {
+    plugins: &'a [&'static str],
+    systems: &'a [[Timing; SCHEDULE_NUM]],
+    schedules: &'a [Timing; SCHEDULE_NUM],
+}
+
+/// Statistics of one plugin system, as iterated by `SystemStats::systems`.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemEntry {
+    pub schedule: &'static str,
+    pub plugin: &'static str,
+    pub system: &'static str,
+    pub stats: TimingStats,
+}
+
+impl<'a> SystemStats<'a> {
+    /// Names of the plugins, in their collection order.
+    pub fn plugins(&self) -> &'a [&'static str] {
+        self.plugins
+    }
+
+    /// Statistics of the system `plugin` runs in `schedule`, e.g.
+    /// `stats.system("Update", "PhysicsPlugin")`.
+    pub fn system(&self, schedule: &str, plugin: &str) -> Option<TimingStats> {
+        let schedule = SCHEDULES.iter().position(|s| *s == schedule)?;
+        let plugin = self.plugins.iter().position(|p| *p == plugin)?;
+        Some(self.systems[plugin][schedule].get())
+    }
+
+    /// Statistics of a whole schedule, e.g. `stats.schedule("Update")`.
+    pub fn schedule(&self, schedule: &str) -> Option<TimingStats> {
+        let schedule = SCHEDULES.iter().position(|s| *s == schedule)?;
+        Some(self.schedules[schedule].get())
+    }
+
+    /// Every plugin system, grouped by plugin, in schedule order.
+    pub fn systems(&self) -> impl Iterator<Item = SystemEntry> + 'a {
+        self.systems
+            .iter()
+            .zip(self.plugins)
+            .flat_map(|(timings, &plugin)| {
+                timings
+                    .iter()
+                    .enumerate()
+                    .map(move |(idx, timing)| SystemEntry {
+                        schedule: SCHEDULES[idx],
+                        plugin,
+                        system: SYSTEMS[idx],
+                        stats: timing.get(),
+                    })
+            })
+    }
+
+    /// Every schedule, in order.
+    pub fn schedules(&self) -> impl Iterator<Item = (&'static str, TimingStats)> + 'a {
+        SCHEDULES
+            .into_iter()
+            .zip(self.schedules.iter().map(Timing::get))
+    }
+
+    /// Clears all the statistics.
+    pub fn reset(&self) {
+        for timing in self.systems.iter().flatten().chain(self.schedules) {
+            timing.reset();
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Deterministic test harness for plugin collections (`testing` feature).
+//!
+//! A [`TestApp`] runs the schedules of an [`App`] one frame at a time, on
+//! the calling thread, so assertions can inspect the shared data and the
+//! plugins (the fields of the generated collection, named after their
+//! types in lowercase) between frames:
+//!
+//! ```rust
+//! use typed_ecs::{
+//!     macros::generate_collection, plugin::Plugin, shared_data::SharedData,
+//!     testing::TestApp,
+//! };
+//!
+//! #[derive(Default)]
+//! struct Counter(u32);
+//!
+//! impl SharedData for Counter {
+//!     fn build() -> Self {
+//!         Self::default()
+//!     }
+//! }
+//!
+//! struct IncrementPlugin {
+//!     async_runs: u32,
+//! }
+//!
+//! impl Plugin<Counter> for IncrementPlugin {
+//!     fn build() -> Self {
+//!         Self { async_runs: 0 }
+//!     }
+//!
+//!     async fn async_update(&mut self, _sd: &Counter) {
+//!         self.async_runs += 1;
+//!     }
+//!
+//!     fn apply_update(&mut self, sd: &mut Counter) {
+//!         sd.0 += 1;
+//!     }
+//! }
+//!
+//! generate_collection!(IncrementPlugin);
+//! let mut test = TestApp::new(build_generated_collection::<Counter>());
+//!
+//! assert_eq!(test.run(3), None);
+//! assert_eq!(test.frame(), 3);
+//! assert_eq!(test.shared_data().0, 3);
+//! assert_eq!(test.collection().incrementplugin.async_runs, 3);
+//!
+//! test.shared_data_mut().0 = 10;
+//! test.tick();
+//! assert_eq!(test.shared_data().0, 11);
+//! ```
+//!
+//! The async schedules (and the background tasks) are driven by
+//! [`block_on`], which parks the thread until woken: futures relying on
+//! the reactor of a runtime, such as `tokio::time::sleep`, won't complete.
+//! With the `parallel` feature, build the collection with
+//! `build_generated_collection_with(Sequential)` for the systems to run in
+//! their declaration order.
+
+use core::{
+    future::Future,
+    pin::{Pin, pin},
+    task::{Context, Poll, Waker},
+};
+use std::{boxed::Box, sync::Arc, task::Wake, thread::Thread};
+
+use crate::{
+    app::App,
+    background::CollectionTasks,
+    executor::{DefaultExecutor, ExecutorTrait},
+    instrumentation::{DefaultInstrumentation, Instrumentation},
+    plugin_collection::PluginCollection,
+    shared_data::SharedData,
+    should_exit::ExitReason,
+};
+
+/// Runs `future` to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+/// An app under test, run frame by frame. See the [module docs](self).
+///
+/// The executor of the app is never run: `TestApp` drives the frames
+/// itself.
+pub struct TestApp<
+    SD: SharedData + 'static,
+    PC: PluginCollection<SD> + 'static,
+    E: ExecutorTrait + 'static = DefaultExecutor,
+    I: Instrumentation + 'static = DefaultInstrumentation,
+> {
+    app: App<SD, PC, E, I>,
+    // Created after the startup schedules, as with `App::run`.
+    tasks: Option<Pin<Box<dyn CollectionTasks<SD, PC>>>>,
+    frame: u64,
+    exit: Option<ExitReason>,
+}
+
+impl<SD: SharedData + 'static, PC: PluginCollection<SD> + 'static> TestApp<SD, PC> {
+    /// Wraps `collection` in an app, with a fresh `SD::build()` shared
+    /// data. Nothing runs before [`TestApp::startup`] or the first frame.
+    pub fn new(collection: PC) -> Self {
+        Self::from_app(App::new(collection))
+    }
+}
+
+impl<
+    SD: SharedData + 'static,
+    PC: PluginCollection<SD> + 'static,
+    E: ExecutorTrait + 'static,
+    I: Instrumentation + 'static,
+> TestApp<SD, PC, E, I>
+{
+    /// Wraps an existing app, e.g. with a custom shared data or
+    /// instrumentation.
+    pub fn from_app(app: App<SD, PC, E, I>) -> Self {
+        Self {
+            app,
+            tasks: None,
+            frame: 0,
+            exit: None,
+        }
+    }
+
+    /// Runs the startup schedules, unless they already ran.
+    pub fn startup(&mut self) {
+        if self.tasks.is_some() {
+            return;
+        }
+        block_on(self.app.run_startup());
+        self.tasks = Some(Box::pin(self.app.background_tasks()));
+    }
+
+    /// Runs one frame (after the startup schedules, on the first call).
+    /// Returns the exit reason if a plugin requested exit, in which case
+    /// the frame stopped right after the ExitCheck schedule.
+    ///
+    /// Panics if the app has already exited.
+    pub fn tick(&mut self) -> Option<ExitReason> {
+        assert!(
+            self.exit.is_none(),
+            "TestApp::tick called after the app exited"
+        );
+        self.startup();
+
+        let tasks = self.tasks.as_mut().expect("created by startup");
+        let should_exit = block_on(self.app.run_frame(tasks.as_mut()));
+        self.frame += 1;
+
+        if should_exit {
+            self.exit = Some(self.app.exit_reason());
+        }
+        self.exit.clone()
+    }
+
+    /// Runs up to `frames` frames, stopping early if a plugin requests
+    /// exit.
+    pub fn run(&mut self, frames: u64) -> Option<ExitReason> {
+        for _ in 0..frames {
+            if let Some(reason) = self.tick() {
+                return Some(reason);
+            }
+        }
+        None
+    }
+
+    /// Runs the OnExit schedule (once). Otherwise, it runs when the
+    /// `TestApp` is dropped.
+    pub fn shutdown(&mut self) {
+        self.app.run_shutdown();
+    }
+
+    /// Number of frames run so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Why the app exited, if it did.
+    pub fn exit_reason(&self) -> Option<&ExitReason> {
+        self.exit.as_ref()
+    }
+
+    pub fn shared_data(&self) -> &SD {
+        &self.app.shared_data
+    }
+
+    pub fn shared_data_mut(&mut self) -> &mut SD {
+        &mut self.app.shared_data
+    }
+
+    /// The plugins, as fields of the generated collection.
+    pub fn collection(&self) -> &PC {
+        &self.app.plugin_collection
+    }
+
+    pub fn collection_mut(&mut self) -> &mut PC {
+        &mut self.app.plugin_collection
+    }
+
+    pub fn app(&mut self) -> &mut App<SD, PC, E, I> {
+        &mut self.app
+    }
+}
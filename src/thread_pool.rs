@@ -0,0 +1,171 @@
+use std::{format, string::String, sync::Arc};
+
+pub use rayon::ThreadPoolBuildError;
+
+use crate::fork_join::ForkJoin;
+
+/// Rayon backend of [`ForkJoin`], used by the generated collection to run
+/// the non-applying systems of a schedule in parallel (`parallel` feature).
+///
+/// By default, the rayon global pool is used. A dedicated pool can be
+/// given to the app with `App::with_thread_pool`, either built from a
+/// [`ThreadPoolConfig`], or supplied directly as a `rayon::ThreadPool`.
+#[derive(Default, Clone)]
+pub enum EcsThreadPool {
+    /// Rayon's global thread pool.
+    #[default]
+    Global,
+    /// A pool owned (or shared) by the app.
+    Custom(Arc<rayon::ThreadPool>),
+}
+
+impl EcsThreadPool {
+    /// Number of worker threads of the underlying pool.
+    pub fn current_num_threads(&self) -> usize {
+        match self {
+            Self::Global => rayon::current_num_threads(),
+            Self::Custom(pool) => pool.current_num_threads(),
+        }
+    }
+}
+
+impl ForkJoin for EcsThreadPool {
+    #[inline(always)]
+    fn join<A, B>(&self, a: A, b: B)
+    where
+        A: FnOnce() + Send,
+        B: FnOnce() + Send,
+    {
+        match self {
+            Self::Global => rayon::join(a, b),
+            Self::Custom(pool) => pool.join(a, b),
+        };
+    }
+}
+
+impl From<rayon::ThreadPool> for EcsThreadPool {
+    fn from(pool: rayon::ThreadPool) -> Self {
+        Self::Custom(Arc::new(pool))
+    }
+}
+
+impl From<Arc<rayon::ThreadPool>> for EcsThreadPool {
+    fn from(pool: Arc<rayon::ThreadPool>) -> Self {
+        Self::Custom(pool)
+    }
+}
+
+/// Builder for the ECS worker threads.
+///
+/// Every unset option falls back on rayon's default, except the thread
+/// name, which defaults to `ecs-worker-{i}`.
+///
+/// Core pinning isn't done by the crate itself (it would require an
+/// OS-specific dependency): use [`ThreadPoolConfig::start_handler`] to pin
+/// the worker `i` with the affinity crate of your choice, e.g. with a
+/// captured list of core IDs:
+///
+/// ```rust
+/// use std::sync::{
+///     Arc,
+///     atomic::{AtomicUsize, Ordering},
+/// };
+///
+/// use typed_ecs::thread_pool::ThreadPoolConfig;
+///
+/// let cores = [2, 3];
+/// let started = Arc::new(AtomicUsize::new(0));
+/// let pool = ThreadPoolConfig::new()
+///     .num_threads(cores.len())
+///     .thread_name(move |i| format!("physics-{}", cores[i]))
+///     .start_handler({
+///         let started = started.clone();
+///         // `core_affinity::set_for_current(cores[i])`, or the like.
+///         move |_i| {
+///             started.fetch_add(1, Ordering::SeqCst);
+///         }
+///     })
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(pool.current_num_threads(), 2);
+/// # while started.load(Ordering::SeqCst) < 2 {
+/// #     std::thread::yield_now();
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ThreadPoolConfig {
+    num_threads: usize,
+    stack_size: Option<usize>,
+    thread_name: Option<Arc<dyn Fn(usize) -> String + Send + Sync>>,
+    start_handler: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+}
+
+fn default_thread_name(i: usize) -> String {
+    format!("ecs-worker-{i}")
+}
+
+impl ThreadPoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of worker threads. `0` (the default) lets rayon choose,
+    /// which usually means one thread per logical CPU.
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Stack size of every worker thread, in bytes.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Name of the worker thread of index `i`.
+    pub fn thread_name(
+        mut self,
+        thread_name: impl Fn(usize) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.thread_name = Some(Arc::new(thread_name));
+        self
+    }
+
+    /// Called on each worker thread, with its index, right after it
+    /// starts. This is the place to pin workers to cores.
+    pub fn start_handler(mut self, start_handler: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.start_handler = Some(Arc::new(start_handler));
+        self
+    }
+
+    fn builder(self) -> rayon::ThreadPoolBuilder {
+        let mut builder = rayon::ThreadPoolBuilder::new().num_threads(self.num_threads);
+        builder = match self.thread_name {
+            Some(thread_name) => builder.thread_name(move |i| thread_name(i)),
+            None => builder.thread_name(default_thread_name),
+        };
+        if let Some(stack_size) = self.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        if let Some(start_handler) = self.start_handler {
+            builder = builder.start_handler(move |i| start_handler(i));
+        }
+        builder
+    }
+
+    /// Builds a pool dedicated to the app.
+    pub fn build(self) -> Result<EcsThreadPool, ThreadPoolBuildError> {
+        self.builder().build().map(EcsThreadPool::from)
+    }
+
+    /// Configures rayon's global pool, and returns it for
+    /// `App::with_thread_pool`. Fails if it has already been initialized
+    /// (explicitly, or by any previous use of rayon): the crate never
+    /// builds it on its own.
+    pub fn build_global(self) -> Result<EcsThreadPool, ThreadPoolBuildError> {
+        self.builder()
+            .build_global()
+            .map(|()| EcsThreadPool::Global)
+    }
+}
@@ -0,0 +1,84 @@
+use core::{future::Future, pin::pin, time::Duration};
+
+use futures::future::{Either, select};
+
+/// Clock the collection awaits the async systems deadlines with (see
+/// `Plugin::ASYNC_TIMEOUT`). It is injected into a collection with
+/// `GeneratedPluginCollection::with_timer`, so any async runtime can
+/// provide one: `tokio::time::sleep`, `embassy_time::Timer::after`, ...
+pub trait Timer {
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()>;
+
+    /// Called once an async system or task has exceeded its deadline,
+    /// before the plugin's own `on_async_timeout` hook.
+    #[inline(always)]
+    fn on_timeout(&self, _report: &TimeoutReport) {}
+}
+
+/// Default timer of a collection: never fires, so deadlines are ignored
+/// until a real timer is injected.
+#[derive(Default, Clone, Copy)]
+pub struct NoTimer;
+
+impl Timer for NoTimer {
+    #[inline(always)]
+    fn sleep(&self, _duration: Duration) -> impl Future<Output = ()> {
+        core::future::pending()
+    }
+}
+
+/// What happens to the async task of a plugin (see `Plugin::async_task`)
+/// that exceeds its deadline.
+///
+/// The future of an async system is always dropped: it borrows the plugin
+/// and the SharedData, which the apply systems then mutate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnTimeout {
+    /// The task is dropped, and the schedule goes on without it.
+    Cancel,
+    /// The schedule goes on without the task, which is polled again in the
+    /// next frames (no other one being started meanwhile). Its result is
+    /// applied once it completes, each frame waiting for it until the
+    /// deadline again.
+    CarryOver,
+}
+
+/// Which async system exceeded its deadline.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeoutReport {
+    pub schedule: &'static str,
+    pub plugin: &'static str,
+    pub system: &'static str,
+    pub timeout: Duration,
+}
+
+/// Awaits `future`, racing it against `timer` when a `timeout` is set,
+/// and drops it past the deadline. Returns whether the deadline has been
+/// exceeded.
+///
+/// Called by the generated collection for every async system.
+#[inline(always)]
+pub async fn with_timeout<T: Timer, F: Future<Output = ()>>(
+    timer: &T,
+    timeout: Option<Duration>,
+    (schedule, plugin, system): (&'static str, &'static str, &'static str),
+    future: F,
+) -> bool {
+    let Some(timeout) = timeout else {
+        future.await;
+        return false;
+    };
+
+    match select(pin!(future), pin!(timer.sleep(timeout))).await {
+        Either::Left(((), _)) => false,
+        Either::Right(((), _)) => {
+            timer.on_timeout(&TimeoutReport {
+                schedule,
+                plugin,
+                system,
+                timeout,
+            });
+            true
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Async tasks outliving their frame, and background tasks owned by their
+//! app.
+
+use std::{
+    future::poll_fn,
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use typed_ecs::{
+    app::App,
+    futures::{Stream, StreamExt, stream},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    timeout::{OnTimeout, Timer},
+};
+
+#[derive(Default)]
+struct Frames {
+    frames: u32,
+    applied_in: Option<u32>,
+    received: u32,
+}
+
+impl SharedData for Frames {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// Every deadline is exceeded as soon as the task is pending.
+struct ExpiredTimer;
+
+impl Timer for ExpiredTimer {
+    fn sleep(&self, _duration: Duration) -> impl Future<Output = ()> {
+        std::future::ready(())
+    }
+}
+
+struct FramePlugin;
+
+impl Plugin<Frames> for FramePlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Frames) {
+        sd.frames += 1;
+    }
+}
+
+/// An async task completing once `done` is set.
+struct DiskPlugin<const CARRY_OVER: bool> {
+    done: Arc<AtomicBool>,
+    started: u32,
+    timeouts: u32,
+}
+
+impl<const CARRY_OVER: bool> Plugin<Frames> for DiskPlugin<CARRY_OVER> {
+    const ASYNC_TIMEOUT: Option<Duration> = Some(Duration::from_millis(1));
+    const ON_ASYNC_TIMEOUT: OnTimeout = if CARRY_OVER {
+        OnTimeout::CarryOver
+    } else {
+        OnTimeout::Cancel
+    };
+
+    fn build() -> Self {
+        Self {
+            done: Arc::new(AtomicBool::new(false)),
+            started: 0,
+            timeouts: 0,
+        }
+    }
+
+    fn async_task(
+        &mut self,
+        _sd: &Frames,
+    ) -> Option<
+        impl Future<Output = impl FnOnce(&mut Self, &mut Frames) + use<CARRY_OVER>> + use<CARRY_OVER>,
+    > {
+        self.started += 1;
+        let done = self.done.clone();
+        Some(poll_fn(move |_| {
+            if done.load(Ordering::Relaxed) {
+                Poll::Ready(|_: &mut Self, sd: &mut Frames| sd.applied_in = Some(sd.frames))
+            } else {
+                Poll::Pending
+            }
+        }))
+    }
+
+    fn on_async_timeout(&mut self, system: &'static str, _sd: &Frames) {
+        assert_eq!(system, "async_task");
+        self.timeouts += 1;
+    }
+}
+
+type CarryOverPlugin = DiskPlugin<true>;
+type CancelPlugin = DiskPlugin<false>;
+
+#[tokio::test]
+async fn carried_over_task_lets_frames_advance() {
+    generate_collection!(FramePlugin, CarryOverPlugin);
+
+    let mut app = App::new(build_generated_collection::<Frames>().with_timer(ExpiredTimer));
+    app.run_startup().await;
+    let mut tasks = pin!(app.background_tasks());
+
+    for _ in 0..3 {
+        app.run_frame(tasks.as_mut()).await;
+    }
+    let plugin = &app.plugin_collection.carryoverplugin;
+    assert_eq!(app.shared_data.frames, 3);
+    assert_eq!(
+        plugin.started, 1,
+        "no other task while the first one is pending"
+    );
+    assert_eq!(plugin.timeouts, 3);
+    assert_eq!(app.shared_data.applied_in, None);
+
+    plugin.done.store(true, Ordering::Relaxed);
+    app.run_frame(tasks.as_mut()).await;
+    let plugin = &app.plugin_collection.carryoverplugin;
+    assert_eq!(plugin.started, 1);
+    assert_eq!(plugin.timeouts, 3);
+    assert_eq!(app.shared_data.applied_in, Some(4));
+
+    app.run_frame(tasks.as_mut()).await;
+    assert_eq!(app.plugin_collection.carryoverplugin.started, 2);
+}
+
+#[tokio::test]
+async fn cancelled_task_is_started_again() {
+    generate_collection!(FramePlugin, CancelPlugin);
+
+    let mut app = App::new(build_generated_collection::<Frames>().with_timer(ExpiredTimer));
+    app.run_startup().await;
+    let mut tasks = pin!(app.background_tasks());
+
+    for _ in 0..3 {
+        app.run_frame(tasks.as_mut()).await;
+    }
+    let plugin = &app.plugin_collection.cancelplugin;
+    assert_eq!(plugin.started, 3);
+    assert_eq!(plugin.timeouts, 3);
+    assert_eq!(app.shared_data.applied_in, None);
+}
+
+/// Hands `factor`, then twice and three times it, to the shared data.
+struct ReaderPlugin {
+    factor: u32,
+}
+
+impl Plugin<Frames> for ReaderPlugin {
+    fn build() -> Self {
+        Self { factor: 1 }
+    }
+
+    fn background_task(
+        &mut self,
+    ) -> impl Stream<Item = impl FnOnce(&mut Self, &mut Frames) + use<>> + use<> {
+        let factor = self.factor;
+        stream::iter(1..=3)
+            .map(move |i| move |_: &mut Self, sd: &mut Frames| sd.received += i * factor)
+    }
+}
+
+#[tokio::test]
+async fn background_tasks_belong_to_their_app() {
+    generate_collection!(ReaderPlugin);
+
+    let mut first = App::new(build_generated_collection::<Frames>());
+    let mut second = App::new(build_generated_collection::<Frames>());
+    second.plugin_collection.readerplugin.factor = 10;
+    first.run_startup().await;
+    second.run_startup().await;
+    let mut first_tasks = pin!(first.background_tasks());
+    let mut second_tasks = pin!(second.background_tasks());
+
+    // No async system: the tasks are still polled, and one result applied,
+    // every frame.
+    first.run_frame(first_tasks.as_mut()).await;
+    second.run_frame(second_tasks.as_mut()).await;
+    assert_eq!(first.shared_data.received, 1);
+    assert_eq!(second.shared_data.received, 10);
+
+    for _ in 0..2 {
+        first.run_frame(first_tasks.as_mut()).await;
+        second.run_frame(second_tasks.as_mut()).await;
+    }
+    assert_eq!(first.shared_data.received, 1 + 2 + 3);
+    assert_eq!(second.shared_data.received, 10 + 20 + 30);
+}
@@ -0,0 +1,95 @@
+//! The OnExit schedule runs exactly once, whether the app is run, dropped
+//! or both.
+
+use std::cell::{Cell, RefCell};
+
+use typed_ecs::{
+    app::App,
+    executor::ExecutorTrait,
+    instrumentation::Instrumentation,
+    macros::generate_collection,
+    plugin::Plugin,
+    plugin_collection::PluginCollection,
+    shared_data::{PhantomSharedData, SharedData},
+    should_exit::{ExitReason, ShouldExit},
+};
+
+thread_local! {
+    static ON_EXIT_RUNS: Cell<u32> = const { Cell::new(0) };
+    static REASONS: RefCell<Vec<ExitReason>> = const { RefCell::new(Vec::new()) };
+}
+
+struct ExitPlugin;
+
+impl<SD: SharedData> Plugin<SD> for ExitPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        should_exit.request_exit();
+    }
+
+    fn on_exit(&mut self, _sd: &SD) {
+        ON_EXIT_RUNS.set(ON_EXIT_RUNS.get() + 1);
+    }
+}
+
+generate_collection!(ExitPlugin);
+
+/// Logs the reasons it's handed, and runs the OnExit schedule unless
+/// `SKIP`.
+struct LoggingExecutor<const SKIP: bool>;
+
+impl<const SKIP: bool> ExecutorTrait for LoggingExecutor<SKIP> {
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+        reason: &ExitReason,
+    ) {
+        REASONS.with_borrow_mut(|reasons| reasons.push(reason.clone()));
+        if !SKIP {
+            app.run_shutdown();
+        }
+    }
+}
+
+type LoggedApp<const SKIP: bool> =
+    App<PhantomSharedData, GeneratedPluginCollection<PhantomSharedData>, LoggingExecutor<SKIP>>;
+
+fn app<const SKIP: bool>() -> LoggedApp<SKIP> {
+    App::new_with_executor(build_generated_collection(), LoggingExecutor)
+}
+
+#[tokio::test]
+async fn run_app_runs_on_exit_once() {
+    let mut app = app::<false>();
+    assert_eq!(app.run().await, ExitReason::Requested);
+    drop(app);
+    assert_eq!(ON_EXIT_RUNS.get(), 1);
+    assert_eq!(REASONS.take(), [ExitReason::Requested]);
+}
+
+#[test]
+fn dropped_app_runs_on_exit() {
+    drop(app::<false>());
+    assert_eq!(ON_EXIT_RUNS.get(), 1);
+    assert_eq!(REASONS.take(), [ExitReason::Interrupted]);
+}
+
+#[tokio::test]
+async fn skipped_on_exit_stays_skipped() {
+    let mut app = app::<true>();
+    app.run().await;
+    drop(app);
+    assert_eq!(ON_EXIT_RUNS.get(), 0);
+    assert_eq!(
+        REASONS.take(),
+        [ExitReason::Requested, ExitReason::Interrupted]
+    );
+}
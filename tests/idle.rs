@@ -0,0 +1,84 @@
+//! `IdleExecutor` awaiting its `Wait` between idle frames.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use typed_ecs::{
+    app::App,
+    idle::{Idle, IdleExecutor, Wait},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+#[derive(Default)]
+struct Frames(u32);
+
+impl SharedData for Frames {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// Idle for 5ms after odd frames, busy after even ones; exits after 10.
+struct BlinkPlugin;
+
+impl Plugin<Frames> for BlinkPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Frames) {
+        sd.0 += 1;
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Frames) {
+        if sd.0 == 10 {
+            should_exit.request_exit();
+        }
+    }
+
+    fn idle(&self, sd: &Frames) -> Idle {
+        if sd.0 % 2 == 1 {
+            Idle::For(Duration::from_millis(5))
+        } else {
+            Idle::Busy
+        }
+    }
+}
+
+/// Returns at once, counting the waits: not `Default`, it shares its
+/// count with the test.
+struct CountingWait {
+    waits: Arc<AtomicU32>,
+}
+
+impl Wait for CountingWait {
+    async fn wait(&mut self, timeout: Option<Duration>) {
+        assert_eq!(timeout, Some(Duration::from_millis(5)));
+        self.waits.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[tokio::test]
+async fn executor_waits_after_idle_frames() {
+    generate_collection!(BlinkPlugin);
+    let waits = Arc::new(AtomicU32::new(0));
+    let mut app = App::new_with_executor(
+        build_generated_collection::<Frames>(),
+        IdleExecutor::new(CountingWait {
+            waits: waits.clone(),
+        }),
+    );
+    app.run().await;
+
+    assert_eq!(app.shared_data.0, 10);
+    // After frames 1, 3, 5, 7 and 9: never after the last one.
+    assert_eq!(waits.load(Ordering::Relaxed), 5);
+}
@@ -0,0 +1,111 @@
+//! `IsrQueue`: order, capacity and dropped items.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use typed_ecs::inject::IsrQueue;
+
+#[test]
+fn items_keep_their_order_across_laps() {
+    // Not a power of two, for the slots to be reused at other offsets on
+    // every lap.
+    let queue = IsrQueue::<u32, 3>::new();
+    let mut expected = VecDeque::new();
+    let mut next = 0;
+
+    // Pushes and pops 1 to 3 items at a time, going around the queue
+    // many times.
+    for round in 0..100 {
+        for _ in 0..round % 3 + 1 {
+            if expected.len() < 3 {
+                queue.push(next).unwrap();
+                expected.push_back(next);
+                next += 1;
+            }
+        }
+        assert_eq!(queue.len(), expected.len());
+        for _ in 0..(round + 1) % 3 + 1 {
+            assert_eq!(queue.pop(), expected.pop_front());
+        }
+    }
+    assert!(next > 30, "{next} items pushed");
+    assert_eq!(queue.dropped(), 0);
+}
+
+#[test]
+fn full_queue_drops_and_counts() {
+    let queue = IsrQueue::<u32, 2>::new();
+    queue.push(1).unwrap();
+    queue.push(2).unwrap();
+    assert_eq!(queue.push(3), Err(3));
+    assert_eq!(queue.push(4), Err(4));
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.dropped(), 2);
+
+    // Room again once popped, the count staying.
+    assert_eq!(queue.pop(), Some(1));
+    queue.push(5).unwrap();
+    assert_eq!(queue.push(6), Err(6));
+    assert_eq!(queue.dropped(), 3);
+
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.pop(), None);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn dropping_the_queue_drops_its_items() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Item;
+
+    impl Drop for Item {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let queue = IsrQueue::<Item, 4>::new();
+    for _ in 0..4 {
+        assert!(queue.push(Item).is_ok());
+    }
+    // Going around the queue.
+    drop(queue.pop());
+    drop(queue.pop());
+    assert!(queue.push(Item).is_ok());
+    assert!(queue.push(Item).is_ok());
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+    drop(queue);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 6);
+}
+
+#[test]
+fn producer_and_consumer_threads_keep_the_order() {
+    static QUEUE: IsrQueue<u32, 4> = IsrQueue::new();
+    const ITEMS: u32 = 10_000;
+
+    let producer = std::thread::spawn(|| {
+        for item in 0..ITEMS {
+            while QUEUE.push(item).is_err() {
+                std::thread::yield_now();
+            }
+        }
+    });
+
+    let mut expected = 0;
+    while expected < ITEMS {
+        match QUEUE.pop() {
+            Some(item) => {
+                assert_eq!(item, expected);
+                expected += 1;
+            }
+            None => std::thread::yield_now(),
+        }
+    }
+    producer.join().unwrap();
+    assert!(QUEUE.is_empty());
+}
@@ -0,0 +1,94 @@
+//! The names plugins log under.
+
+use std::marker::PhantomData;
+
+use typed_ecs::{
+    app::App,
+    logging::{self, RingBuffer},
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::{PhantomSharedData, SharedData},
+};
+
+static LOGS: RingBuffer<8> = RingBuffer::new();
+
+struct Temperature;
+struct Pressure;
+
+/// Logs once built, whatever it counts.
+struct CounterPlugin<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<SD: SharedData, T> Plugin<SD> for CounterPlugin<T> {
+    fn build() -> Self {
+        typed_ecs::info!("built");
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+struct RendererPlugin;
+
+impl<SD: SharedData> Plugin<SD> for RendererPlugin {
+    const NAME: &'static str = "physics::Renderer";
+
+    fn build() -> Self {
+        typed_ecs::info!("built");
+        Self
+    }
+}
+
+struct Sensors;
+
+impl SharedData for Sensors {
+    fn build() -> Self {
+        Self
+    }
+}
+
+/// Only for `Sensors`, its shared data type inferred by the macro.
+struct SensorPlugin;
+
+impl Plugin<Sensors> for SensorPlugin {
+    const NAME: &'static str = "sensors::SensorPlugin";
+
+    fn build() -> Self {
+        typed_ecs::info!(SD = _; "built");
+        Self
+    }
+}
+
+type TemperatureCounter = CounterPlugin<Temperature>;
+type PressureCounter = CounterPlugin<Pressure>;
+
+fn drained() -> Vec<&'static str> {
+    let mut plugins = Vec::new();
+    LOGS.drain(|entry| plugins.push(entry.plugin));
+    plugins
+}
+
+#[test]
+fn plugins_log_under_their_name() {
+    logging::set_sink(&LOGS).unwrap();
+
+    // Without `NAME`, the full type name, generics included, in a
+    // collection or not.
+    let _ = <CounterPlugin<Pressure> as Plugin<PhantomSharedData>>::build();
+    assert_eq!(drained(), ["logging::CounterPlugin<logging::Pressure>"]);
+
+    generate_collection!(TemperatureCounter, PressureCounter, RendererPlugin);
+    let _app = App::new(build_generated_collection::<PhantomSharedData>());
+    assert_eq!(
+        drained(),
+        [
+            "logging::CounterPlugin<logging::Temperature>",
+            "logging::CounterPlugin<logging::Pressure>",
+            "physics::Renderer",
+        ]
+    );
+
+    let _ = <SensorPlugin as Plugin<Sensors>>::build();
+    assert_eq!(drained(), ["sensors::SensorPlugin"]);
+}
@@ -0,0 +1,169 @@
+//! What happens to a plugin whose system panics, per `Plugin::ON_PANIC`.
+
+use std::pin::pin;
+
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    panic_isolation::OnPanic,
+    plugin::Plugin,
+    shared_data::SharedData,
+    should_exit::{ExitReason, ShouldExit},
+};
+
+#[derive(Default)]
+struct Frames {
+    frames: u32,
+    rebuilt_reads: u32,
+    skipped_applies: u32,
+}
+
+impl SharedData for Frames {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+struct FramePlugin;
+
+impl Plugin<Frames> for FramePlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Frames) {
+        sd.frames += 1;
+    }
+}
+
+/// Panics on its second read.
+struct RebuiltPlugin {
+    reads: u32,
+}
+
+impl Plugin<Frames> for RebuiltPlugin {
+    const ON_PANIC: OnPanic = OnPanic::Rebuild;
+
+    fn build() -> Self {
+        Self { reads: 0 }
+    }
+
+    fn update(&mut self, _sd: &Frames) {
+        self.reads += 1;
+        if self.reads == 2 {
+            panic!("read failed");
+        }
+    }
+
+    fn apply_update(&mut self, sd: &mut Frames) {
+        sd.rebuilt_reads = self.reads;
+    }
+}
+
+/// Panics in its first PostUpdate.
+struct SkippedPlugin;
+
+impl Plugin<Frames> for SkippedPlugin {
+    const ON_PANIC: OnPanic = OnPanic::Skip;
+
+    fn build() -> Self {
+        Self
+    }
+
+    fn post_update(&mut self, _sd: &Frames) {
+        panic!("out of bounds");
+    }
+
+    fn apply_post_update(&mut self, sd: &mut Frames) {
+        sd.skipped_applies += 1;
+    }
+}
+
+/// Panics in the ApplyUpdate of the third frame.
+struct CriticalPlugin;
+
+impl Plugin<Frames> for CriticalPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Frames) {
+        if sd.frames == 3 {
+            panic!("invariant broken at frame {}", sd.frames);
+        }
+    }
+}
+
+struct ExitAfterFiveFramesPlugin;
+
+impl Plugin<Frames> for ExitAfterFiveFramesPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Frames) {
+        if sd.frames == 5 {
+            should_exit.request_exit();
+        }
+    }
+}
+
+fn silence_panics() {
+    std::panic::set_hook(Box::new(|_| {}));
+}
+
+#[tokio::test]
+async fn rebuilt_plugin_starts_over() {
+    silence_panics();
+    generate_collection!(FramePlugin, RebuiltPlugin);
+
+    let mut app = App::new(build_generated_collection::<Frames>());
+    app.run_startup().await;
+    let mut tasks = pin!(app.background_tasks());
+
+    app.run_frame(tasks.as_mut()).await;
+    assert_eq!(app.shared_data.rebuilt_reads, 1);
+    assert_eq!(app.take_panic(), None);
+    // Panics on the second read: a fresh plugin takes over, whose
+    // ApplyUpdate runs in the same frame.
+    app.run_frame(tasks.as_mut()).await;
+    assert_eq!(app.shared_data.rebuilt_reads, 0);
+    let report = app.take_panic().expect("the panic is reported");
+    assert_eq!(report.plugin, "RebuiltPlugin");
+    assert_eq!(report.system, "update");
+    assert_eq!(report.message, "read failed");
+    app.run_frame(tasks.as_mut()).await;
+    assert_eq!(app.shared_data.rebuilt_reads, 1);
+    assert_eq!(app.shared_data.frames, 3);
+    assert_eq!(app.take_panic(), None);
+}
+
+#[tokio::test]
+async fn skipped_plugin_stays_poisoned() {
+    silence_panics();
+    generate_collection!(FramePlugin, SkippedPlugin, ExitAfterFiveFramesPlugin);
+
+    let mut app = App::new(build_generated_collection::<Frames>());
+    assert_eq!(app.run().await, ExitReason::Requested);
+    // The other plugins went on, the panicking one never ran again.
+    assert_eq!(app.shared_data.frames, 5);
+    assert_eq!(app.shared_data.skipped_applies, 0);
+    assert_eq!(app.take_panic().unwrap().message, "out of bounds");
+}
+
+#[tokio::test]
+async fn critical_plugin_stops_the_app() {
+    silence_panics();
+    generate_collection!(FramePlugin, CriticalPlugin, ExitAfterFiveFramesPlugin);
+
+    let mut app = App::new(build_generated_collection::<Frames>());
+    let ExitReason::PluginPanicked(report) = app.run().await else {
+        panic!("CriticalPlugin should have stopped the app");
+    };
+    assert_eq!(report.schedule, "ApplyUpdate");
+    assert_eq!(report.plugin, "CriticalPlugin");
+    assert_eq!(report.system, "apply_update");
+    assert_eq!(report.message, "invariant broken at frame 3");
+    // The frame the panic happened in is the last one.
+    assert_eq!(app.shared_data.frames, 3);
+}
@@ -0,0 +1,246 @@
+//! Replaying recorded logs, and rejecting the ones that don't match.
+
+use typed_ecs::{
+    app::App,
+    executor::ExecutorTrait,
+    instrumentation::Instrumentation,
+    macros::generate_collection,
+    plugin::Plugin,
+    plugin_collection::PluginCollection,
+    replay::{Decoder, Encoder, RecordExecutor, ReplayError, ReplayExecutor},
+    shared_data::SharedData,
+    should_exit::{ExitReason, ShouldExit},
+};
+
+#[derive(Default)]
+struct World {
+    // Written by InputPlugin: recorded.
+    input: i64,
+    // Derived from the input by SumPlugin.
+    sum: i64,
+}
+
+impl SharedData for World {
+    fn build() -> Self {
+        Self::default()
+    }
+
+    fn record(&self, out: &mut Encoder) {
+        out.put_i64(self.input);
+    }
+
+    fn replay(&mut self, input: &mut Decoder<'_>) -> Result<(), ReplayError> {
+        self.input = input.get_i64()?;
+        Ok(())
+    }
+}
+
+/// Nothing recorded: every record of its log is a single byte.
+#[derive(Default)]
+struct Nothing;
+
+impl SharedData for Nothing {
+    fn build() -> Self {
+        Self
+    }
+}
+
+/// Exits after `FRAMES` frames.
+struct ExitPlugin<const FRAMES: u32> {
+    frames: u32,
+}
+
+impl<SD: SharedData, const FRAMES: u32> Plugin<SD> for ExitPlugin<FRAMES> {
+    fn build() -> Self {
+        Self { frames: 0 }
+    }
+
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, _sd: &SD) {
+        self.frames += 1;
+        if self.frames == FRAMES {
+            should_exit.request_exit();
+        }
+    }
+}
+
+struct InputPlugin(i64);
+
+impl Plugin<World> for InputPlugin {
+    fn build() -> Self {
+        Self(0)
+    }
+
+    fn apply_pre_update(&mut self, sd: &mut World) {
+        self.0 += 1;
+        sd.input = self.0 * 7 % 5;
+    }
+}
+
+struct SumPlugin;
+
+impl Plugin<World> for SumPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut World) {
+        sd.sum += sd.input;
+    }
+}
+
+type ExitAfterTen = ExitPlugin<10>;
+type ExitAfterThree = ExitPlugin<3>;
+
+/// Records 10 frames, returning the log and the final sum.
+async fn recorded() -> (Vec<u8>, i64) {
+    generate_collection!(InputPlugin, SumPlugin, ExitAfterTen);
+    let mut log = Vec::new();
+    let executor = RecordExecutor::new(&mut log);
+    let mut app = App::new_with_executor(build_generated_collection::<World>(), executor);
+    assert_eq!(app.run().await, ExitReason::Requested);
+    assert!(app.executor_mut().take_error().is_none());
+    let sum = app.shared_data.sum;
+    drop(app);
+    (log, sum)
+}
+
+/// Replays `log` with `collection`, returning the app for its shared data
+/// and the frames replayed.
+async fn replay<SD: SharedData, PC: PluginCollection<SD>>(
+    collection: PC,
+    log: &[u8],
+) -> (App<SD, PC, ReplayExecutor>, Result<u64, ReplayError>) {
+    let mut app = App::new_with_executor(collection, ReplayExecutor::new(log).unwrap());
+    let reason = app.run().await;
+    let result = match app.executor_mut().take_error() {
+        Some(error) => {
+            assert_eq!(reason, ExitReason::Interrupted);
+            Err(error)
+        }
+        None => Ok(app.executor().frames()),
+    };
+    (app, result)
+}
+
+#[tokio::test]
+async fn replayed_run_sees_the_recorded_state() {
+    let (log, recorded_sum) = recorded().await;
+
+    generate_collection!(SumPlugin);
+    let (app, frames) = replay(build_generated_collection::<World>(), &log).await;
+    assert_eq!(frames.unwrap(), 10);
+    assert_eq!(app.shared_data.sum, recorded_sum);
+    assert_ne!(recorded_sum, 0);
+}
+
+#[tokio::test]
+async fn log_cut_short_is_replayed_up_to_its_end() {
+    let (log, _) = recorded().await;
+
+    generate_collection!(SumPlugin);
+    let (_, frames) = replay(build_generated_collection::<World>(), &log[..log.len() / 2]).await;
+    let frames = frames.unwrap();
+    assert!(0 < frames && frames < 10, "{frames} frames replayed");
+}
+
+#[tokio::test]
+async fn foreign_log_is_rejected() {
+    let result = ReplayExecutor::new(&b"not a replay log"[..]).err();
+    assert!(matches!(result, Some(ReplayError::BadHeader)), "{result:?}");
+}
+
+#[tokio::test]
+async fn missing_record_is_a_desync() {
+    generate_collection!(ExitAfterTen);
+    let mut log = Vec::new();
+    let executor = RecordExecutor::new(&mut log);
+    App::new_with_executor(build_generated_collection::<Nothing>(), executor)
+        .run()
+        .await;
+
+    // Header, then ApplyStartup and ApplyAsyncStartup, then per frame:
+    // the frame marker and the 6 apply schedules of the frame, but for
+    // the last one, which stops before ApplyAsyncUpdate. Then the exit
+    // marker.
+    let header = log.len() - (2 + 10 * 7 - 1 + 1);
+    // Drops the ApplyPreUpdate record of the second frame.
+    log.remove(header + 2 + 7 + 1);
+
+    let (_, result) = replay(build_generated_collection::<Nothing>(), &log).await;
+    assert!(
+        matches!(
+            result,
+            Err(ReplayError::Desync {
+                frame: 1,
+                schedule: "ApplyPreUpdate",
+            })
+        ),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn app_exiting_early_diverges() {
+    let (log, _) = recorded().await;
+
+    generate_collection!(SumPlugin, ExitAfterThree);
+    let (_, result) = replay(build_generated_collection::<World>(), &log).await;
+    assert!(
+        matches!(result, Err(ReplayError::Diverged { frame: 3 })),
+        "{result:?}"
+    );
+}
+
+/// Counts the frames and the runs it sees through its hooks.
+#[derive(Default)]
+struct CountingExecutor {
+    frames: u32,
+    exits: u32,
+}
+
+impl ExecutorTrait for CountingExecutor {
+    fn after_frame<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        _app: &mut App<SD, PC, Executor, I>,
+        _should_exit: bool,
+    ) {
+        self.frames += 1;
+    }
+
+    fn run_exit_hooks<
+        SD: SharedData,
+        PC: PluginCollection<SD>,
+        Executor: ExecutorTrait,
+        I: Instrumentation,
+    >(
+        &mut self,
+        app: &mut App<SD, PC, Executor, I>,
+        _reason: &ExitReason,
+    ) {
+        self.exits += 1;
+        app.run_shutdown();
+    }
+}
+
+#[tokio::test]
+async fn hooks_of_the_wrapped_executor_are_called() {
+    generate_collection!(InputPlugin, SumPlugin, ExitAfterTen);
+    let mut log = Vec::new();
+    let executor = RecordExecutor::with_executor(&mut log, CountingExecutor::default());
+    let mut app = App::new_with_executor(build_generated_collection::<World>(), executor);
+    app.run().await;
+    assert_eq!(app.executor().inner().frames, 10);
+    assert_eq!(app.executor().inner().exits, 1);
+    drop(app);
+
+    let executor = ReplayExecutor::with_executor(&log[..], CountingExecutor::default()).unwrap();
+    let mut app = App::new_with_executor(build_generated_collection::<World>(), executor);
+    app.run().await;
+    assert_eq!(app.executor().inner().frames, 10);
+    assert_eq!(app.executor().inner().exits, 1);
+}
@@ -0,0 +1,158 @@
+//! The order the systems of a frame run in.
+
+use typed_ecs::{
+    app::App, macros::generate_collection, plugin::Plugin, shared_data::SharedData,
+    should_exit::ShouldExit,
+};
+
+#[derive(Default)]
+struct Frames(u32);
+
+impl SharedData for Frames {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+/// Logs every system it runs.
+struct OrderPlugin {
+    log: Vec<&'static str>,
+}
+
+impl Plugin<Frames> for OrderPlugin {
+    fn build() -> Self {
+        Self { log: Vec::new() }
+    }
+
+    fn startup(&mut self, _sd: &Frames) {
+        self.log.push("startup");
+    }
+    fn apply_startup(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_startup");
+    }
+    async fn async_startup(&mut self, _sd: &Frames) {
+        self.log.push("async_startup");
+    }
+    fn apply_async_startup(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_async_startup");
+    }
+
+    fn pre_update(&mut self, _sd: &Frames) {
+        self.log.push("pre_update");
+    }
+    fn apply_pre_update(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_pre_update");
+    }
+    async fn async_pre_update(&mut self, _sd: &Frames) {
+        self.log.push("async_pre_update");
+    }
+    fn apply_async_pre_update(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_async_pre_update");
+    }
+    fn update(&mut self, _sd: &Frames) {
+        self.log.push("update");
+    }
+    fn apply_update(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_update");
+    }
+    async fn async_update(&mut self, _sd: &Frames) {
+        self.log.push("async_update");
+    }
+    fn apply_async_update(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_async_update");
+    }
+    fn post_update(&mut self, _sd: &Frames) {
+        self.log.push("post_update");
+    }
+    fn apply_post_update(&mut self, _sd: &mut Frames) {
+        self.log.push("apply_post_update");
+    }
+    async fn async_post_update(&mut self, _sd: &Frames) {
+        self.log.push("async_post_update");
+    }
+    fn apply_async_post_update(&mut self, sd: &mut Frames) {
+        self.log.push("apply_async_post_update");
+        sd.0 += 1;
+    }
+    fn exit_check<S: ShouldExit>(&mut self, should_exit: &mut S, sd: &Frames) {
+        self.log.push("exit_check");
+        if sd.0 == 2 {
+            should_exit.request_exit();
+        }
+    }
+
+    fn on_exit(&mut self, _sd: &Frames) {
+        self.log.push("on_exit");
+    }
+}
+
+const FRAME: [&str; 13] = [
+    "pre_update",
+    "apply_pre_update",
+    "async_pre_update",
+    "apply_async_pre_update",
+    "update",
+    "apply_update",
+    "post_update",
+    "apply_post_update",
+    "async_post_update",
+    "apply_async_post_update",
+    "exit_check",
+    "async_update",
+    "apply_async_update",
+];
+
+#[tokio::test]
+async fn systems_run_in_schedule_order() {
+    generate_collection!(OrderPlugin);
+    let mut app = App::new(build_generated_collection::<Frames>());
+    app.run().await;
+
+    let mut expected = vec![
+        "startup",
+        "apply_startup",
+        "async_startup",
+        "apply_async_startup",
+    ];
+    expected.extend(FRAME);
+    // The last frame stops after ExitCheck.
+    expected.extend(&FRAME[..FRAME.len() - 2]);
+    expected.push("on_exit");
+    assert_eq!(app.plugin_collection.orderplugin.log, expected);
+}
+
+#[test]
+fn introspection_lists_the_frame_schedules() {
+    let systems: Vec<_> = typed_ecs::introspection::SCHEDULES
+        .iter()
+        .map(|schedule| schedule.system)
+        .collect();
+    // AsyncUpdate and ApplyAsyncUpdate are listed before ExitCheck, but
+    // run after it.
+    assert_eq!(systems[4..14], FRAME[..10]);
+    assert_eq!(systems[14..16], FRAME[11..]);
+    assert_eq!(systems[16], FRAME[10]);
+}
+
+#[test]
+fn introspection_shows_how_schedules_run() {
+    use typed_ecs::introspection::Execution;
+
+    generate_collection!(OrderPlugin);
+    let info = App::new(build_generated_collection::<Frames>()).collection_info();
+    let parallel = if cfg!(feature = "parallel") {
+        Execution::Parallel
+    } else {
+        Execution::Sequential
+    };
+    // Update, ApplyUpdate, AsyncUpdate, ExitCheck
+    assert_eq!(info.execution[8], parallel);
+    assert_eq!(info.execution[9], Execution::Sequential);
+    assert_eq!(info.execution[14], Execution::Concurrent);
+    assert_eq!(info.execution[16], Execution::Sequential);
+
+    // `OrderPlugin` doesn't declare its systems.
+    assert_eq!(info.plugins_in(8).count(), 0);
+    assert_eq!(info.unknown_plugins().count(), 1);
+    assert!(info.table().to_string().contains("unknown"));
+}
@@ -0,0 +1,193 @@
+//! Snapshots round trip, and the incompatible ones are rejected.
+
+use serde::{Deserialize, Serialize};
+use typed_ecs::{
+    app::App,
+    macros::generate_collection,
+    plugin::Plugin,
+    shared_data::SharedData,
+    snapshot::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Game {
+    score: u32,
+}
+
+impl SharedData for Game {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+impl Snapshot for Game {
+    const SCHEMA: &'static str = "game";
+    const VERSION: u32 = 2;
+}
+
+/// Same layout as `Game`, of a previous version.
+#[derive(Default, Serialize, Deserialize)]
+struct OldGame {
+    score: u32,
+}
+
+impl SharedData for OldGame {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+impl Snapshot for OldGame {
+    const SCHEMA: &'static str = "game";
+    const VERSION: u32 = 1;
+}
+
+/// Same layout and version as `Game`, of another schema.
+#[derive(Default, Serialize, Deserialize)]
+struct Editor {
+    score: u32,
+}
+
+impl SharedData for Editor {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+impl Snapshot for Editor {
+    const SCHEMA: &'static str = "editor";
+    const VERSION: u32 = 2;
+}
+
+struct ComboPlugin {
+    combo: u16,
+}
+
+impl<SD: SharedData> Plugin<SD> for ComboPlugin {
+    fn build() -> Self {
+        Self { combo: 0 }
+    }
+
+    fn save_state(&self, out: &mut SnapshotWriter<'_>) -> Result<(), SnapshotError> {
+        out.put(&self.combo)
+    }
+
+    fn load_state(
+        input: &mut SnapshotReader<'_>,
+    ) -> Result<impl FnOnce(&mut Self) + use<SD>, SnapshotError> {
+        let combo = input.get()?;
+        Ok(move |plugin: &mut Self| plugin.combo = combo)
+    }
+}
+
+struct IdlePlugin;
+
+impl<SD: SharedData> Plugin<SD> for IdlePlugin {
+    fn build() -> Self {
+        Self
+    }
+}
+
+generate_collection!(ComboPlugin);
+
+mod modded {
+    use super::*;
+    generate_collection!(ComboPlugin, IdlePlugin);
+}
+
+fn save(buf: &mut [u8]) -> &[u8] {
+    let mut app = App::new(build_generated_collection::<Game>());
+    app.shared_data.score = 1200;
+    app.plugin_collection.comboplugin.combo = 7;
+    app.save_snapshot(buf).unwrap()
+}
+
+#[test]
+fn snapshot_round_trips() {
+    let mut buf = [0; 64];
+    let bytes = save(&mut buf);
+
+    let mut app = App::new(build_generated_collection::<Game>());
+    app.load_snapshot(bytes).unwrap();
+    assert_eq!(app.shared_data.score, 1200);
+    assert_eq!(app.plugin_collection.comboplugin.combo, 7);
+}
+
+#[test]
+fn other_version_is_rejected() {
+    let mut buf = [0; 64];
+    let bytes = save(&mut buf);
+
+    let mut app = App::new(build_generated_collection::<OldGame>());
+    assert_eq!(
+        app.load_snapshot(bytes),
+        Err(SnapshotError::Version {
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(app.shared_data.score, 0);
+}
+
+#[test]
+fn other_schema_is_rejected() {
+    let mut buf = [0; 64];
+    let bytes = save(&mut buf);
+
+    let mut app = App::new(build_generated_collection::<Editor>());
+    assert_eq!(app.load_snapshot(bytes), Err(SnapshotError::Layout));
+    assert_eq!(app.shared_data.score, 0);
+}
+
+#[test]
+fn other_plugins_are_rejected() {
+    let mut buf = [0; 64];
+    let bytes = save(&mut buf);
+
+    let mut app = App::new(modded::build_generated_collection::<Game>());
+    assert_eq!(app.load_snapshot(bytes), Err(SnapshotError::Layout));
+    assert_eq!(app.shared_data.score, 0);
+}
+
+#[test]
+fn corrupted_snapshots_are_rejected() {
+    let mut buf = [0; 64];
+    let bytes = save(&mut buf).to_vec();
+    let mut app = App::new(build_generated_collection::<Game>());
+
+    assert_eq!(
+        app.load_snapshot(b"not a snapshot"),
+        Err(SnapshotError::BadHeader)
+    );
+    assert_eq!(
+        app.load_snapshot(&bytes[..bytes.len() - 1]),
+        Err(SnapshotError::Invalid)
+    );
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(app.load_snapshot(&trailing), Err(SnapshotError::Invalid));
+}
+
+#[test]
+fn corrupted_plugin_state_leaves_the_app_untouched() {
+    let mut buf = [0; 64];
+    let mut bytes = save(&mut buf).to_vec();
+    // The last byte is the combo, of the last section: the shared data
+    // decodes, the plugin state doesn't.
+    *bytes.last_mut().unwrap() = 0x80;
+
+    let mut app = App::new(build_generated_collection::<Game>());
+    app.plugin_collection.comboplugin.combo = 3;
+    assert_eq!(app.load_snapshot(&bytes), Err(SnapshotError::Invalid));
+    assert_eq!(app.shared_data.score, 0);
+    assert_eq!(app.plugin_collection.comboplugin.combo, 3);
+}
+
+#[test]
+fn small_buffer_is_full() {
+    let app = App::new(build_generated_collection::<Game>());
+    assert_eq!(
+        app.save_snapshot(&mut [0; 8]).map(|bytes| bytes.len()),
+        Err(SnapshotError::BufferFull)
+    );
+}
@@ -0,0 +1,83 @@
+//! Initializing a `StaticCell` once, in place or not.
+
+use std::{mem::MaybeUninit, panic};
+
+use typed_ecs::{
+    app::App, macros::generate_collection, plugin::Plugin, shared_data::SharedData,
+    static_app::StaticCell,
+};
+
+#[test]
+fn second_init_is_refused() {
+    static CELL: StaticCell<u32> = StaticCell::new();
+    assert!(!CELL.is_initialized());
+
+    let value = CELL.init(1);
+    assert!(CELL.is_initialized());
+    assert!(CELL.try_init_with(|| unreachable!()).is_none());
+    assert!(CELL.try_init_in_place(|_| unreachable!()).is_none());
+    assert!(panic::catch_unwind(|| CELL.init(2)).is_err());
+    assert_eq!(*value, 1);
+}
+
+#[test]
+fn panicking_init_leaves_the_cell_claimed() {
+    static CELL: StaticCell<u32> = StaticCell::new();
+
+    assert!(panic::catch_unwind(|| CELL.init_with(|| panic!("no value"))).is_err());
+    assert!(!CELL.is_initialized());
+    assert!(CELL.try_init_with(|| 1).is_none());
+    assert!(!CELL.is_initialized());
+}
+
+#[test]
+fn value_built_out_of_place_is_refused() {
+    static CELL: StaticCell<u32> = StaticCell::new();
+    static OTHER: StaticCell<u32> = StaticCell::new();
+
+    let other = OTHER.init(1);
+    let result = panic::catch_unwind(|| {
+        CELL.init_in_place(|_: &mut MaybeUninit<u32>| &mut *Box::leak(Box::new(2)))
+    });
+    assert!(result.is_err());
+    assert!(!CELL.is_initialized());
+    assert_eq!(*other, 1);
+}
+
+#[derive(Default)]
+struct Counter(u32);
+
+impl SharedData for Counter {
+    fn build() -> Self {
+        Self(10)
+    }
+}
+
+struct BufferPlugin {
+    buffer: [u8; 256],
+}
+
+impl Plugin<Counter> for BufferPlugin {
+    fn build() -> Self {
+        Self { buffer: [7; 256] }
+    }
+
+    fn apply_update(&mut self, sd: &mut Counter) {
+        sd.0 += 1;
+    }
+}
+
+generate_collection!(BufferPlugin);
+
+type BufferApp = App<Counter, GeneratedPluginCollection<Counter>>;
+
+#[test]
+fn app_built_in_place() {
+    static APP: StaticCell<BufferApp> = StaticCell::new();
+
+    let app = APP.init_in_place(|slot| App::new_in(slot, build_generated_collection_in));
+    assert!(APP.is_initialized());
+    assert_eq!(app.shared_data.0, 10);
+    assert_eq!(app.plugin_collection.bufferplugin.buffer, [7; 256]);
+    assert_eq!(app.collection_info().plugins.len(), 1);
+}
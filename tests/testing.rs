@@ -0,0 +1,69 @@
+//! `TestApp` around apps with a custom executor and instrumentation.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use typed_ecs::{
+    app::App, guard::NoopGuard, instrumentation::Instrumentation, macros::generate_collection,
+    plugin::Plugin, shared_data::SharedData, testing::TestApp,
+};
+
+#[derive(Default)]
+struct Counter(u32);
+
+impl SharedData for Counter {
+    fn build() -> Self {
+        Self::default()
+    }
+}
+
+struct IncrementPlugin;
+
+impl Plugin<Counter> for IncrementPlugin {
+    fn build() -> Self {
+        Self
+    }
+
+    fn apply_update(&mut self, sd: &mut Counter) {
+        sd.0 += 1;
+    }
+}
+
+/// Counts the Update schedules run.
+#[derive(Default)]
+struct UpdateCounter {
+    updates: AtomicU32,
+}
+
+impl Instrumentation for UpdateCounter {
+    fn on_schedule_start(&self, schedule: &'static str) -> impl Drop {
+        if schedule == "Update" {
+            self.updates.fetch_add(1, Ordering::Relaxed);
+        }
+        NoopGuard
+    }
+
+    fn on_system_start(
+        &self,
+        _schedule: &'static str,
+        _plugin: &'static str,
+        _system: &'static str,
+    ) -> impl Drop {
+        NoopGuard
+    }
+}
+
+#[test]
+fn custom_instrumentation_sees_every_frame() {
+    generate_collection!(IncrementPlugin);
+
+    let app = App::new(build_generated_collection::<Counter>())
+        .with_instrumentation(UpdateCounter::default());
+    let mut test = TestApp::from_app(app);
+
+    assert_eq!(test.run(4), None);
+    assert_eq!(test.shared_data().0, 4);
+    assert_eq!(
+        test.app().instrumentation.updates.load(Ordering::Relaxed),
+        4
+    );
+}
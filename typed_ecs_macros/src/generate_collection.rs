@@ -1,7 +1,29 @@
-use crate::schedule_contents::generate_schedule;
+use crate::schedule_contents::{execution, generate_schedule};
 use proc_macro2::TokenStream;
 use quote::quote;
 
+/// Plugin systems, in the order of their schedules.
+pub(crate) const SYSTEMS: [&str; 18] = [
+    "startup",
+    "apply_startup",
+    "async_startup",
+    "apply_async_startup",
+    "pre_update",
+    "apply_pre_update",
+    "async_pre_update",
+    "apply_async_pre_update",
+    "update",
+    "apply_update",
+    "post_update",
+    "apply_post_update",
+    "async_post_update",
+    "apply_async_post_update",
+    "async_update",
+    "apply_async_update",
+    "exit_check",
+    "on_exit",
+];
+
 pub(crate) fn generate_plugin_collection_impl(
     fields: Vec<syn::Ident>,
     types: Vec<syn::Ident>,
@@ -15,38 +37,31 @@ pub(crate) fn generate_plugin_collection_impl(
         "ApplyAsyncStartup",
         "PreUpdate",
         "ApplyPreUpdate",
+        "AsyncPreUpdate",
+        "ApplyAsyncPreUpdate",
         "Update",
         "ApplyUpdate",
         "PostUpdate",
         "ApplyPostUpdate",
+        "AsyncPostUpdate",
+        "ApplyAsyncPostUpdate",
         "AsyncUpdate",
         "ApplyAsyncUpdate",
         "ExitCheck",
         "OnExit",
     ];
 
-    let systems: &[&'static str] = &[
-        "startup",
-        "apply_startup",
-        "async_startup",
-        "apply_async_startup",
-        "pre_update",
-        "apply_pre_update",
-        "update",
-        "apply_update",
-        "post_update",
-        "apply_post_update",
-        "async_update",
-        "apply_async_update",
-        "exit_check",
-        "on_exit",
-    ];
-
     let mut impl_contents = quote! {};
 
-    for (schedule_name, system_name) in schedules.iter().zip(systems) {
-        let generated_schedule =
-            generate_schedule(fields.clone(), types.clone(), schedule_name, system_name);
+    // The schedule index is the one of `typed_ecs::system_stats::SCHEDULES`.
+    for (schedule_idx, (schedule_name, system_name)) in schedules.iter().zip(SYSTEMS).enumerate() {
+        let generated_schedule = generate_schedule(
+            fields.clone(),
+            types.clone(),
+            schedule_name,
+            system_name,
+            schedule_idx,
+        );
         impl_contents = quote! {
             #impl_contents
 
@@ -55,15 +70,437 @@ pub(crate) fn generate_plugin_collection_impl(
     }
 
     let plugin_num: usize = types.len();
+    let is_parallel = crate::IS_PARALLEL;
+    let executions = SYSTEMS.map(execution);
+    // The tasks of a plugin are named after it, to report their deadlines.
+    let task_names = (0..plugin_num).map(|idx| {
+        if crate::HAS_ASYNC_TIMEOUTS {
+            quote! { Self::PLUGINS[#idx].name, }
+        } else {
+            quote! {}
+        }
+    });
+    // With the `background-tasks` feature, the tasks of the plugins, as a
+    // balanced tree. None otherwise: `()`.
+    let tasks = if crate::HAS_BACKGROUND_TASKS {
+        let tasks_tree = tasks_tree(&fields);
+        quote! {
+            #(
+                let #fields = ::typed_ecs::background::plugin_tasks::<SD, #types, Self>(
+                    |collection| &mut collection.#fields,
+                    #task_names
+                    self,
+                );
+            )*
+            #tasks_tree
+        }
+    } else {
+        quote! {}
+    };
+
+    // `Plugin::NAME`, falling back to the type.
+    let names: Vec<TokenStream> = types
+        .iter()
+        .map(|ty| {
+            quote! {
+                ::typed_ecs::introspection::plugin_name(
+                    <#ty as ::typed_ecs::plugin::Plugin<SD>>::NAME,
+                    stringify!(#ty),
+                )
+            }
+        })
+        .collect();
+
+    // With the `parallel` feature, the collection carries the fork-join
+    // backend its non-applying schedules are run on.
+    let fj = if crate::IS_PARALLEL {
+        ForkJoinTokens {
+            generics: quote! { , FJ },
+            default_generics: quote! { , FJ = ::typed_ecs::fork_join::DefaultForkJoin },
+            bound: quote! { FJ: ::typed_ecs::fork_join::ForkJoin, },
+            field: quote! { _fork_join: FJ, },
+            init: quote! { fork_join },
+            moved: quote! { _fork_join: self._fork_join, },
+            assoc: quote! {
+                type ForkJoin = FJ;
+
+                #[inline(always)]
+                fn fork_join(&self) -> &FJ {
+                    &self._fork_join
+                }
+
+                #[inline(always)]
+                fn set_fork_join(&mut self, fork_join: FJ) {
+                    self._fork_join = fork_join;
+                }
+            },
+        }
+    } else {
+        ForkJoinTokens::default()
+    };
+    let ForkJoinTokens {
+        generics,
+        default_generics,
+        bound,
+        field,
+        init,
+        moved,
+        assoc,
+    } = fj;
+
+    // With the `panic-isolation` feature, the collection tracks which of
+    // its plugins are poisoned, and why it has to exit.
+    let (iso_field, iso_init, iso_moved, iso_assoc) = if crate::IS_PANIC_ISOLATED {
+        (
+            quote! { _isolation: ::typed_ecs::panic_isolation::Isolation<#plugin_num>, },
+            quote! { ::typed_ecs::panic_isolation::Isolation::new() },
+            quote! { _isolation: self._isolation, },
+            quote! {
+                #[inline(always)]
+                fn take_panic(&mut self) -> Option<::typed_ecs::panic_isolation::PanicReport> {
+                    self._isolation.take_panic()
+                }
+
+                #[inline(always)]
+                fn take_exit_panic(&mut self) -> Option<::typed_ecs::panic_isolation::PanicReport> {
+                    self._isolation.take_exit_panic()
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    };
+
+    // With the `system-stats` feature, the collection times its systems
+    // and schedules.
+    let (stats_field, stats_init, stats_moved, stats_assoc) = if crate::HAS_SYSTEM_STATS {
+        (
+            quote! { _stats: ::typed_ecs::system_stats::StatsTable<#plugin_num>, },
+            quote! { ::typed_ecs::system_stats::StatsTable::new([#(#names),*]) },
+            quote! { _stats: self._stats, },
+            quote! {
+                #[inline(always)]
+                fn system_stats(&self) -> ::typed_ecs::system_stats::SystemStats<'_> {
+                    self._stats.view()
+                }
+
+                #[inline(always)]
+                fn system_stats_all(&mut self, sd: &SD) {
+                    let stats = self._stats.view();
+                    #( self.#fields.on_system_stats(&stats, sd); )*
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    };
+
+    // With the `async-timeout` feature, the collection awaits the deadlines
+    // of its async systems and tasks with a timer.
+    let (t, t_default, t_bound) = if crate::HAS_ASYNC_TIMEOUTS {
+        (
+            quote! { T, },
+            quote! { T = ::typed_ecs::timeout::NoTimer, },
+            quote! { T: ::typed_ecs::timeout::Timer, },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {})
+    };
+    let (timer_field, timer_init, timer_moved) = if crate::HAS_ASYNC_TIMEOUTS {
+        (
+            quote! { _timer: T, },
+            quote! { ::typed_ecs::timeout::NoTimer },
+            quote! { _timer: self._timer, },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {})
+    };
+
+    // With the `budget` feature, the collection checks the time budgets of
+    // its systems and schedules with a watchdog, and counts the violations.
+    let (w, w_default, w_bound) = if crate::HAS_BUDGETS {
+        (
+            quote! { W },
+            quote! { W = ::typed_ecs::budget::NoWatchdog, },
+            quote! { W: ::typed_ecs::budget::Watchdog, },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {})
+    };
+    let (budget_field, watchdog_init, budget_init, budget_moved) = if crate::HAS_BUDGETS {
+        (
+            quote! {
+                _watchdog: W,
+                _budget: ::typed_ecs::budget::BudgetState<#plugin_num>,
+            },
+            quote! { ::typed_ecs::budget::NoWatchdog },
+            quote! { ::typed_ecs::budget::BudgetState::new() },
+            quote! {
+                _watchdog: self._watchdog,
+                _budget: self._budget,
+            },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {}, quote! {})
+    };
+    let budget_assoc = if crate::HAS_BUDGETS {
+        quote! {
+            #[inline(always)]
+            fn take_budget_violation(&mut self) -> Option<::typed_ecs::budget::BudgetViolation> {
+                self._budget.take_violation()
+            }
+
+            #[inline(always)]
+            fn budget_violations(&self) -> u32 {
+                self._budget.violations()
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let with_timer = if crate::HAS_ASYNC_TIMEOUTS {
+        quote! {
+            /// Replaces the timer the async systems deadlines are awaited with.
+            pub fn with_timer<T2: ::typed_ecs::timeout::Timer>(
+                self,
+                timer: T2,
+            ) -> GeneratedPluginCollection<SD #generics, T2, #w> {
+                GeneratedPluginCollection {
+                    #(#fields: self.#fields,)*
+                    #moved
+                    #iso_moved
+                    #stats_moved
+                    #budget_moved
+                    _timer: timer,
+                    _marker: ::core::marker::PhantomData
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let with_watchdog = if crate::HAS_BUDGETS {
+        quote! {
+            /// Replaces the watchdog the time budgets are checked with.
+            pub fn with_watchdog<W2: ::typed_ecs::budget::Watchdog>(
+                self,
+                watchdog: W2,
+            ) -> GeneratedPluginCollection<SD #generics, #t W2> {
+                GeneratedPluginCollection {
+                    #(#fields: self.#fields,)*
+                    #moved
+                    #iso_moved
+                    #stats_moved
+                    #timer_moved
+                    _watchdog: watchdog,
+                    _budget: self._budget,
+                    _marker: ::core::marker::PhantomData
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // With the `serde` feature, the collection saves and restores the state
+    // of its plugins, in sections checked against their fingerprint.
+    let snapshot_assoc = if crate::HAS_SNAPSHOTS {
+        quote! {
+            const SNAPSHOT_FINGERPRINT: u64 = {
+                let hash = ::typed_ecs::snapshot::FINGERPRINT_SEED;
+                #( let hash = ::typed_ecs::snapshot::fingerprint(hash, stringify!(#types).as_bytes()); )*
+                hash
+            };
 
-    let expanded = quote! {
-        pub struct GeneratedPluginCollection<SD> {
+            fn save_state_all(
+                &self,
+                out: &mut ::typed_ecs::snapshot::SnapshotWriter<'_>,
+            ) -> Result<(), ::typed_ecs::snapshot::SnapshotError> {
+                #( out.section(|out| self.#fields.save_state(out))?; )*
+                Ok(())
+            }
+
+            fn load_state_all(
+                &mut self,
+                input: &mut ::typed_ecs::snapshot::SnapshotReader<'_>,
+                shared_data: &mut SD,
+            ) -> Result<(), ::typed_ecs::snapshot::SnapshotError>
+            where
+                SD: ::typed_ecs::snapshot::Snapshot,
+            {
+                input.check_sections(1 + #plugin_num)?;
+                let decoded: SD = input.section(|input| input.get())?;
+                #( let #fields = input.section(<#types as ::typed_ecs::plugin::Plugin<SD>>::load_state)?; )*
+                *shared_data = decoded;
+                #( #fields(&mut self.#fields); )*
+                Ok(())
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let build_fn = quote! {
+        pub fn build_generated_collection<SD>()
+        -> GeneratedPluginCollection<SD>
+        where
+        SD: ::typed_ecs::shared_data::SharedData,
+            #( #types: ::typed_ecs::plugin::Plugin<SD>, )*
+    };
+
+    // The state the framework keeps along the plugins, and its initial
+    // value (none for the fields of disabled features).
+    let (state_fields, state_inits): (Vec<TokenStream>, Vec<TokenStream>) = [
+        (quote! { _fork_join }, init),
+        (quote! { _isolation }, iso_init),
+        (quote! { _stats }, stats_init),
+        (quote! { _timer }, timer_init),
+        (quote! { _watchdog }, watchdog_init),
+        (quote! { _budget }, budget_init),
+        (quote! { _marker }, quote! { ::core::marker::PhantomData }),
+    ]
+    .into_iter()
+    .filter(|(_, init)| !init.is_empty())
+    .unzip();
+
+    // Builds the collection into a slot, one field at a time: only one
+    // plugin at a time is on the stack.
+    let build_in_fn = quote! {
+        pub fn build_generated_collection_in<SD>(
+            slot: &mut ::core::mem::MaybeUninit<GeneratedPluginCollection<SD>>,
+        ) -> &mut GeneratedPluginCollection<SD>
+        where
+        SD: ::typed_ecs::shared_data::SharedData,
+            #( #types: ::typed_ecs::plugin::Plugin<SD>, )*
+    };
+    let build_in = quote! {
+        let collection = slot.as_mut_ptr();
+        // SAFETY: every field is written before the slot is assumed
+        // initialized.
+        unsafe {
+            #( (&raw mut (*collection).#fields).write(#types::build()); )*
+            #( (&raw mut (*collection).#state_fields).write(#state_inits); )*
+            &mut *collection
+        }
+    };
+
+    let build = if crate::IS_PARALLEL {
+        quote! {
+            #build_fn
+            {
+                build_generated_collection_with(::core::default::Default::default())
+            }
+
+            pub fn build_generated_collection_with<SD, FJ>(fork_join: FJ)
+            -> GeneratedPluginCollection<SD, FJ>
+            where
+            SD: ::typed_ecs::shared_data::SharedData,
+            #bound
+                #( #types: ::typed_ecs::plugin::Plugin<SD>, )*
+            {
+                GeneratedPluginCollection::<SD, FJ> {
+                    #(#fields: #types::build(),)*
+                    #(#state_fields: #state_inits,)*
+                }
+            }
+
+            #build_in_fn
+            {
+                let fork_join = ::typed_ecs::fork_join::DefaultForkJoin::default();
+                #build_in
+            }
+        }
+    } else {
+        quote! {
+            #build_fn
+            {
+                GeneratedPluginCollection::<SD> {
+                    #(#fields: #types::build(),)*
+                    #(#state_fields: #state_inits,)*
+                }
+            }
+
+            #build_in_fn
+            {
+                #build_in
+            }
+        }
+    };
+
+    // Each plugin implementing `Requires` has its dependencies checked
+    // against the types of the collection, and each pair of plugins their
+    // `Conflicts`, at compile time.
+    let (conflicting, conflicted): (Vec<_>, Vec<_>) = types
+        .iter()
+        .flat_map(|p| types.iter().filter(move |x| *x != p).map(move |x| (p, x)))
+        .unzip();
+    let dependencies = quote! {
+        const _: () = {
+            struct Members;
+            #(
+                impl<By> ::typed_ecs::dependencies::Contains<#types, By> for Members {}
+            )*
+
+            #[allow(dead_code)]
+            fn check_dependencies() {
+                #[allow(unused_imports)]
+                use ::typed_ecs::dependencies::{
+                    ViaConflicts as _, ViaNoConflicts as _, ViaNothing as _, ViaRequires as _,
+                };
+                #(
+                    (&&::typed_ecs::dependencies::Probe::<#types>::new()).dependencies().check::<Members>();
+                )*
+                #(
+                    (&&::typed_ecs::dependencies::Pair::<#conflicting, #conflicted>::new()).conflict().check();
+                )*
+            }
+        };
+    };
+
+    quote! {
+        pub struct GeneratedPluginCollection<
+            SD #default_generics,
+            #t_default
+            #w_default
+        > {
             #(#quote_fields,)*
+            #field
+            #iso_field
+            #stats_field
+            #timer_field
+            #budget_field
             _marker: ::core::marker::PhantomData<SD>
         }
 
-        impl <SD>::typed_ecs::plugin_collection::PluginCollection<SD> for GeneratedPluginCollection<SD>
+        impl<SD #generics, #t #w> GeneratedPluginCollection<SD #generics, #t #w> {
+            #with_timer
+
+            #with_watchdog
+        }
+
+        impl<SD #generics, #t #w> GeneratedPluginCollection<SD #generics, #t #w>
+        where
+            SD: ::typed_ecs::shared_data::SharedData,
+            #( #types: ::typed_ecs::plugin::Plugin<SD>, )*
+        {
+            /// The plugins, in their collection order.
+            pub const PLUGINS: [::typed_ecs::introspection::PluginInfo; #plugin_num] = [#(
+                ::typed_ecs::introspection::PluginInfo {
+                    name: #names,
+                    version: <#types as ::typed_ecs::plugin::Plugin<SD>>::VERSION,
+                    description: <#types as ::typed_ecs::plugin::Plugin<SD>>::DESCRIPTION,
+                    systems: <#types as ::typed_ecs::plugin::Plugin<SD>>::SYSTEMS,
+                    footprint: ::typed_ecs::introspection::Footprint::of::<#types>(),
+                },
+            )*];
+        }
+
+        impl <SD #generics, #t #w>::typed_ecs::plugin_collection::PluginCollection<SD> for GeneratedPluginCollection<SD #generics, #t #w>
         where SD: ::typed_ecs::shared_data::SharedData,
+        #bound
+        #t_bound
+        #w_bound
         // Even if this appears to do nothing as the hard check is done
         // in build_generated_collection, never remove it: it allows
         // lazy trait evaluation.
@@ -71,21 +508,72 @@ pub(crate) fn generate_plugin_collection_impl(
         {
             const PLUGIN_NUM: usize = #plugin_num;
 
+            const INFO: ::typed_ecs::introspection::CollectionInfo =
+                ::typed_ecs::introspection::CollectionInfo {
+                    plugins: &Self::PLUGINS,
+                    parallel: #is_parallel,
+                    execution: [#(#executions),*],
+                    collection: ::typed_ecs::introspection::Footprint::of::<Self>(),
+                    shared_data: ::typed_ecs::introspection::Footprint::of::<SD>(),
+                };
+
+            #assoc
+
+            #iso_assoc
+
+            #stats_assoc
+
+            #snapshot_assoc
+
+            #budget_assoc
+
+            #[inline(always)]
+            fn idle_all(&self, sd: &SD) -> ::typed_ecs::idle::Idle {
+                let idle = ::typed_ecs::idle::Idle::UntilEvent;
+                #( let idle = idle.and(self.#fields.idle(sd)); )*
+                idle
+            }
+
+            #[inline(always)]
+            fn background_tasks_all(
+                &mut self,
+            ) -> impl ::typed_ecs::background::CollectionTasks<SD, Self> + use<SD #generics, #t #w> {
+                #tasks
+            }
+
             #impl_contents
         }
 
-        pub fn build_generated_collection<SD>()
-        -> GeneratedPluginCollection<SD>
-        where
-        SD: ::typed_ecs::shared_data::SharedData,
-            #( #types: ::typed_ecs::plugin::Plugin<SD>, )*
-        {
-            GeneratedPluginCollection::<SD> {
-                #(#fields: #types::build(),)*
-                _marker: ::core::marker::PhantomData
-            }
+        #build
+
+        #dependencies
+    }
+}
+
+/// Pairs the tasks of the plugins in two halves, recursively, so that the
+/// tasks of N plugins are a balanced tree of depth log2(N).
+fn tasks_tree(fields: &[syn::Ident]) -> TokenStream {
+    match fields {
+        [] => quote! { () },
+        [field] => quote! { #field },
+        _ => {
+            let (left, right) = fields.split_at(fields.len() / 2);
+            let left = tasks_tree(left);
+            let right = tasks_tree(right);
+            quote! { ::typed_ecs::background::Pair::new(#left, #right) }
         }
-    };
+    }
+}
 
-    TokenStream::from(expanded)
+/// Tokens that only exist when the collection is generic over its
+/// fork-join backend (`parallel` feature of this crate).
+#[derive(Default)]
+struct ForkJoinTokens {
+    generics: TokenStream,
+    default_generics: TokenStream,
+    bound: TokenStream,
+    field: TokenStream,
+    init: TokenStream,
+    moved: TokenStream,
+    assoc: TokenStream,
 }
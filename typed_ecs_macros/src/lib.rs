@@ -6,13 +6,43 @@ pub(crate) const IS_PARALLEL: bool = true;
 #[cfg(not(feature = "parallel"))]
 pub(crate) const IS_PARALLEL: bool = false;
 
+#[cfg(feature = "panic-isolation")]
+pub(crate) const IS_PANIC_ISOLATED: bool = true;
+#[cfg(not(feature = "panic-isolation"))]
+pub(crate) const IS_PANIC_ISOLATED: bool = false;
+
+#[cfg(feature = "system-stats")]
+pub(crate) const HAS_SYSTEM_STATS: bool = true;
+#[cfg(not(feature = "system-stats"))]
+pub(crate) const HAS_SYSTEM_STATS: bool = false;
+
+#[cfg(feature = "budget")]
+pub(crate) const HAS_BUDGETS: bool = true;
+#[cfg(not(feature = "budget"))]
+pub(crate) const HAS_BUDGETS: bool = false;
+
+#[cfg(feature = "async-timeout")]
+pub(crate) const HAS_ASYNC_TIMEOUTS: bool = true;
+#[cfg(not(feature = "async-timeout"))]
+pub(crate) const HAS_ASYNC_TIMEOUTS: bool = false;
+
+#[cfg(feature = "background-tasks")]
+pub(crate) const HAS_BACKGROUND_TASKS: bool = true;
+#[cfg(not(feature = "background-tasks"))]
+pub(crate) const HAS_BACKGROUND_TASKS: bool = false;
+
+#[cfg(feature = "serde")]
+pub(crate) const HAS_SNAPSHOTS: bool = true;
+#[cfg(not(feature = "serde"))]
+pub(crate) const HAS_SNAPSHOTS: bool = false;
+
 use proc_macro::TokenStream;
 use quote::quote;
 
 use quote::format_ident;
 use syn::{punctuated::Punctuated, *};
 
-use crate::generate_collection::generate_plugin_collection_impl;
+use crate::generate_collection::{SYSTEMS, generate_plugin_collection_impl};
 
 /// Please see the [`plugin_collection`](https://github.com/heydocode/typed_ecs/blob/main/examples/plugin_collection.rs) for more details on the usage of this macro.
 #[proc_macro]
@@ -52,3 +82,33 @@ pub fn generate_collection(input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
+
+/// Fills in `Plugin::SYSTEMS` from the systems an `impl Plugin` block
+/// defines, for the introspection of the collections to only show them.
+/// An explicit `const SYSTEMS` is left as is.
+#[proc_macro_attribute]
+pub fn systems(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut item = parse_macro_input!(item as ItemImpl);
+
+    let declared = item
+        .items
+        .iter()
+        .any(|item| matches!(item, ImplItem::Const(c) if c.ident == "SYSTEMS"));
+    if !declared {
+        let systems: Vec<String> = item
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                ImplItem::Fn(f) => Some(f.sig.ident.to_string()),
+                _ => None,
+            })
+            .filter(|name| SYSTEMS.contains(&name.as_str()))
+            .collect();
+        item.items.push(parse_quote! {
+            const SYSTEMS: ::typed_ecs::introspection::Systems =
+                ::typed_ecs::introspection::Systems::NONE #( .with(#systems) )*;
+        });
+    }
+
+    TokenStream::from(quote! { #item })
+}
@@ -1,11 +1,28 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
+/// How `generate_schedule` runs the systems of a schedule, as an
+/// `introspection::Execution`.
+pub(crate) fn execution(system_name: &str) -> TokenStream {
+    let execution = if system_name.starts_with("async_") {
+        quote! { Concurrent }
+    } else if crate::IS_PARALLEL
+        && !system_name.contains("apply")
+        && !system_name.contains("exit_check")
+    {
+        quote! { Parallel }
+    } else {
+        quote! { Sequential }
+    };
+    quote! { ::typed_ecs::introspection::Execution::#execution }
+}
+
 pub(crate) fn generate_schedule(
     fields: Vec<syn::Ident>,
     types: Vec<syn::Ident>,
     schedule_name: &'static str,
     system_name: &'static str,
+    schedule_idx: usize,
 ) -> TokenStream {
     let system_group_name = format!("{}_all", system_name);
     let is_async = system_name.starts_with("async_");
@@ -23,96 +40,271 @@ pub(crate) fn generate_schedule(
         );
     }
 
-    if is_async {
+    // One block per plugin: the profiling, timing and budget guards, then
+    // the system call.
+    let systems: Vec<TokenStream> = fields
+        .iter()
+        .zip(&types)
+        .enumerate()
+        .map(|(idx, (field, ty))| {
+            // `Plugin::NAME`, or the type.
+            let name = quote! { Self::PLUGINS[#idx].name };
+            let call = if is_async && crate::HAS_ASYNC_TIMEOUTS {
+                quote! {
+                    let timed_out = ::typed_ecs::timeout::with_timeout(
+                        &self._timer,
+                        <#ty as ::typed_ecs::plugin::Plugin<SD>>::ASYNC_TIMEOUT,
+                        (stringify!(#q_schedule), #name, stringify!(#q_system)),
+                        self.#field.#q_system(sd),
+                    ).await;
+                    if timed_out {
+                        self.#field.on_async_timeout(stringify!(#q_system), sd);
+                    }
+                }
+            } else if is_async {
+                quote! { self.#field.#q_system(sd).await; }
+            } else if exit_check {
+                quote! { self.#field.#q_system(should_exit, sd); }
+            } else {
+                quote! { self.#field.#q_system(sd); }
+            };
+            let call = if crate::IS_PANIC_ISOLATED {
+                isolate(idx, field, ty, &q_schedule, &q_system, is_async, call)
+            } else {
+                call
+            };
+            let stats_guard = if crate::HAS_SYSTEM_STATS {
+                quote! { let _stats_guard = self._stats.system(#idx, #schedule_idx).start(); }
+            } else {
+                quote! {}
+            };
+            let budget_guard = if crate::HAS_BUDGETS {
+                quote! {
+                    let _budget_guard = ::typed_ecs::budget::BudgetGuard::start(
+                        &self._watchdog,
+                        self._budget.plugin(#idx),
+                        <#ty as ::typed_ecs::plugin::Plugin<SD>>::budget(stringify!(#q_system)),
+                        (stringify!(#q_schedule), Some(#name), Some(stringify!(#q_system))),
+                    );
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                let _sys_guard = ::typed_ecs::instrumentation::Instrumentation::on_system_start(
+                    instrumentation,
+                    stringify!(#q_schedule),
+                    #name,
+                    stringify!(#q_system),
+                );
+                #stats_guard
+                #budget_guard
+                #call
+            }
+        })
+        .collect();
+
+    let sched_stats_guard = if crate::HAS_SYSTEM_STATS {
+        quote! { let _sched_stats_guard = self._stats.schedule(#schedule_idx).start(); }
+    } else {
+        quote! {}
+    };
+
+    let sched_budget_guard = if crate::HAS_BUDGETS {
+        quote! {
+            let _sched_budget_guard = ::typed_ecs::budget::BudgetGuard::start(
+                &self._watchdog,
+                self._budget.schedules(),
+                ::typed_ecs::budget::Watchdog::schedule_budget(&self._watchdog, stringify!(#q_schedule)),
+                (stringify!(#q_schedule), None, None),
+            );
+        }
+    } else {
+        quote! {}
+    };
+
+    if is_async && system_name == "async_update" {
+        // The async tasks of the plugins are awaited alongside, until their
+        // deadline with the `async-timeout` feature. Without the
+        // `background-tasks` feature, the tasks are only polled meanwhile.
+        let wait_tasks = if !crate::HAS_BACKGROUND_TASKS {
+            quote! { ::typed_ecs::background::alongside(systems, tasks).await; }
+        } else if crate::HAS_ASYNC_TIMEOUTS {
+            quote! { ::typed_ecs::background::wait_tasks(systems, tasks, &self._timer).await; }
+        } else {
+            quote! { ::typed_ecs::background::wait_tasks(systems, tasks).await; }
+        };
+        quote! {
+            #[inline(always)]
+            async fn #q_group<I: ::typed_ecs::instrumentation::Instrumentation>(
+                &mut self,
+                sd: &SD,
+                instrumentation: &I,
+                tasks: ::core::pin::Pin<&mut dyn ::typed_ecs::background::CollectionTasks<SD, Self>>,
+            ) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
+                let systems = async {
+                    let _ = ::typed_ecs::futures::join! {
+                        #(
+                            async {
+                                #systems
+                            },
+                        )*
+                    };
+                };
+                #wait_tasks
+            }
+        }
+    } else if is_async {
         quote! {
             #[inline(always)]
-            async fn #q_group(&mut self, sd: &SD) {
-                let _sched_guard = Self::on_schedule_start(stringify!(#q_schedule));
+            async fn #q_group<I: ::typed_ecs::instrumentation::Instrumentation>(&mut self, sd: &SD, instrumentation: &I) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
                 let _ = ::typed_ecs::futures::join! {
                     #(
                         async {
-                            let _sys_guard = Self::on_system_start(
-                                stringify!(#q_schedule),
-                                stringify!(#types),
-                                stringify!(#q_system),
-                            );
-                            self.#fields.#q_system(sd).await;
+                            #systems
                         },
                     )*
                 };
             }
         }
-    } else {
-        if exit_check {
+    } else if exit_check {
+        // A panicking plugin whose policy is `OnPanic::Exit` requests exit.
+        let panic_exit = if crate::IS_PANIC_ISOLATED {
             quote! {
-                #[inline(always)]
-                fn #q_group<S: ::typed_ecs::should_exit::ShouldExit>(&mut self, should_exit: &mut S, sd: &SD) {
-                    let _sched_guard = Self::on_schedule_start(stringify!(#q_schedule));
-                    #(
-                        {
-                            let _sys_guard = Self::on_system_start(
-                                stringify!(#q_schedule),
-                                stringify!(#types),
-                                stringify!(#q_system),
-                            );
-                            self.#fields.#q_system(should_exit, sd);
-                        }
-                    )*
+                if self._isolation.exit_requested() {
+                    should_exit.request_exit();
                 }
             }
-        } else if is_mut {
+        } else {
+            quote! {}
+        };
+        // So does a budget violation, when the watchdog decides so.
+        let budget_exit = if crate::HAS_BUDGETS {
             quote! {
-                #[inline(always)]
-                fn #q_group(&mut self, sd: &mut SD) {
-                    let _sched_guard = Self::on_schedule_start(stringify!(#q_schedule));
-                    #(
-                        {
-                            let _sys_guard = Self::on_system_start(
-                                stringify!(#q_schedule),
-                                stringify!(#types),
-                                stringify!(#q_system),
-                            );
-                            self.#fields.#q_system(sd);
-                        }
-                    )*
+                if self._budget.exit_requested() {
+                    should_exit.request_exit();
                 }
             }
         } else {
-            if crate::IS_PARALLEL {
-                quote! {
-                    #[inline(always)]
-                    fn #q_group(&mut self, sd: &SD) {
-                        rayon::scope(|s| {
-                            #(
-                                s.spawn(|_| {
-                                    let _sys_guard = Self::on_system_start(
-                                        stringify!(#q_schedule),
-                                        stringify!(#types),
-                                        stringify!(#q_system),
-                                    );
-                                    self.#fields.#q_system(sd);
-                                });
-                            )*
-                        });
+            quote! {}
+        };
+        quote! {
+            #[inline(always)]
+            fn #q_group<S: ::typed_ecs::should_exit::ShouldExit, I: ::typed_ecs::instrumentation::Instrumentation>(
+                &mut self,
+                should_exit: &mut S,
+                sd: &SD,
+                instrumentation: &I,
+            ) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
+                #(
+                    {
+                        #systems
                     }
-                }
-            } else {
-                quote! {
-                    #[inline(always)]
-                    fn #q_group(&mut self, sd: &SD) {
-                        let _sched_guard = Self::on_schedule_start(stringify!(#q_schedule));
-                        #(
-                            {
-                                let _sys_guard = Self::on_system_start(
-                                    stringify!(#q_schedule),
-                                    stringify!(#types),
-                                    stringify!(#q_system),
-                                );
-                                self.#fields.#q_system(sd);
-                            }
-                        )*
+                )*
+                #panic_exit
+                #budget_exit
+            }
+        }
+    } else if is_mut {
+        quote! {
+            #[inline(always)]
+            fn #q_group<I: ::typed_ecs::instrumentation::Instrumentation>(&mut self, sd: &mut SD, instrumentation: &I) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
+                #(
+                    {
+                        #systems
                     }
-                }
+                )*
+            }
+        }
+    } else if crate::IS_PARALLEL {
+        let tree = fork_join_tree(&systems);
+        quote! {
+            #[inline(always)]
+            fn #q_group<I: ::typed_ecs::instrumentation::Instrumentation>(&mut self, sd: &SD, instrumentation: &I) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
+                #[allow(unused_variables)]
+                let fork_join = &self._fork_join;
+                #tree
+            }
+        }
+    } else {
+        quote! {
+            #[inline(always)]
+            fn #q_group<I: ::typed_ecs::instrumentation::Instrumentation>(&mut self, sd: &SD, instrumentation: &I) {
+                let _sched_guard = ::typed_ecs::instrumentation::Instrumentation::on_schedule_start(instrumentation, stringify!(#q_schedule));
+                #sched_stats_guard
+                #sched_budget_guard
+                #(
+                    {
+                        #systems
+                    }
+                )*
+            }
+        }
+    }
+}
+
+/// Wraps a system call in `catch_unwind` (`panic-isolation` feature). The
+/// systems of a poisoned plugin are skipped.
+fn isolate(
+    idx: usize,
+    field: &syn::Ident,
+    ty: &syn::Ident,
+    q_schedule: &syn::Ident,
+    q_system: &syn::Ident,
+    is_async: bool,
+    call: TokenStream,
+) -> TokenStream {
+    let name = quote! { Self::PLUGINS[#idx].name };
+    let caught = if is_async {
+        quote! { ::typed_ecs::panic_isolation::catch_future(async { #call }).await }
+    } else {
+        quote! { ::typed_ecs::panic_isolation::catch(|| { #call }) }
+    };
+    quote! {
+        if !self._isolation.is_poisoned(#idx) {
+            if let Err(payload) = #caught {
+                self._isolation.handle_panic::<SD, #ty>(
+                    #idx,
+                    &mut self.#field,
+                    (stringify!(#q_schedule), #name, stringify!(#q_system)),
+                    payload,
+                );
+            }
+        }
+    }
+}
+
+/// Splits the systems in two halves, recursively, so that a schedule of N
+/// plugins is a balanced tree of `ForkJoin::join` calls, of depth log2(N).
+fn fork_join_tree(systems: &[TokenStream]) -> TokenStream {
+    match systems {
+        [] => quote! {},
+        [system] => quote! { #system },
+        _ => {
+            let (left, right) = systems.split_at(systems.len() / 2);
+            let left = fork_join_tree(left);
+            let right = fork_join_tree(right);
+            quote! {
+                ::typed_ecs::fork_join::ForkJoin::join(
+                    fork_join,
+                    || { #left },
+                    || { #right },
+                );
             }
         }
     }